@@ -44,6 +44,37 @@ class Params(C.Structure):
         ("max_step_px", C.c_float),
         ("world_w", C.c_float),
         ("world_wrap_mode", C.c_float),
+        ("world_bounce_restitution", C.c_float),
+        ("gravity_blend", C.c_float),
+        ("jump_snap_suppress_frames", C.c_float),
+        ("air_max_speed_falling", C.c_float),
+        ("air_accel_falling", C.c_float),
+        ("noclip", C.c_float),
+        ("max_substeps", C.c_float),
+        ("run_ramp_time", C.c_float),
+        ("disabled_events", C.c_float),
+        ("integration_mode", C.c_float),
+        ("max_rise_speed", C.c_float),
+        ("max_fall_distance", C.c_float),
+        ("coyote_frames", C.c_float),
+        ("jump_buffer_frames", C.c_float),
+        ("freeze_axes", C.c_float),
+        ("min_jump_velocity", C.c_float),
+        ("input_deadzone", C.c_float),
+        ("input_smoothing", C.c_float),
+        ("wall_proximity_gravity_scale", C.c_float),
+        ("auto_fast_fall_after", C.c_float),
+        ("grounded_grace_frames", C.c_float),
+        ("max_step_px_x", C.c_float),
+        ("max_step_px_y", C.c_float),
+        ("world_h", C.c_float),
+        ("foot_width_fraction", C.c_float),
+        ("min_substeps", C.c_float),
+        ("min_max_step", C.c_float),
+        ("auto_bounce", C.c_float),
+        ("bonk_restitution", C.c_float),
+        ("jump_cut_gravity_boost", C.c_float),
+        ("jump_cut_boost_frames", C.c_float),
     ]
 
 class State(C.Structure):
@@ -55,10 +86,66 @@ class State(C.Structure):
         ("coyote", C.c_float),
         ("jump_buffer", C.c_float),
         ("jump_was_down", C.c_ubyte),
+        ("snap_suppress", C.c_ubyte),
+        ("run_ramp", C.c_float),
+        ("hitbox_offset_x", C.c_float),
+        ("hitbox_offset_y", C.c_float),
+        ("hitbox_w", C.c_float),
+        ("hitbox_h", C.c_float),
+        ("just_landed", C.c_ubyte),
+        ("airtime_frames", C.c_uint),
+        ("max_airtime_frames", C.c_uint),
+        ("max_fall_speed", C.c_float),
+        ("distance_x", C.c_float),
+        ("jump_launch_y", C.c_float),
+        ("jump_active", C.c_ubyte),
+        ("last_jump_apex_height", C.c_float),
+        ("pushing", C.c_byte),
+        ("ground_normal_x", C.c_float),
+        ("ground_normal_y", C.c_float),
+        ("fall_distance", C.c_float),
+        ("move_x_smoothed", C.c_float),
+        ("grounded_recently", C.c_ubyte),
+        ("jump_cut_timer", C.c_ubyte),
+    ]
+
+class Input(C.Structure):
+    _fields_ = [
+        ("bits", C.c_ubyte),
+        ("move_x", C.c_float),
+        ("jump_subframe", C.c_float),
     ]
 
 class Events(C.Structure):
-    _fields_ = [("jumped", C.c_ubyte), ("landed", C.c_ubyte), ("bonked", C.c_ubyte)]
+    _fields_ = [
+        ("jumped", C.c_ubyte),
+        ("landed", C.c_ubyte),
+        ("bonked", C.c_ubyte),
+        ("ground_reason", C.c_ubyte),
+        ("fast_falling", C.c_ubyte),
+        ("numeric_fault", C.c_ubyte),
+        ("left_ground", C.c_ubyte),
+        ("fell_too_far", C.c_ubyte),
+        ("jump_wasted", C.c_ubyte),
+        ("land_toi", C.c_float),
+        ("vx_pre_collision", C.c_float),
+        ("vy_pre_collision", C.c_float),
+        ("jump_kind", C.c_ubyte),
+        ("bonk_index", C.c_int),
+        ("wall_index", C.c_int),
+    ]
+
+# Events.ground_reason values must match Rust GroundReason
+GROUND_NONE    = 0
+GROUND_CONTACT = 1
+GROUND_SNAP    = 2
+
+# Events.jump_kind values must match Rust JumpKind
+JUMP_NONE   = 0
+JUMP_GROUND = 1
+JUMP_COYOTE = 2
+JUMP_AIR    = 3
+JUMP_WALL   = 4
 
 # Input bits must match Rust Buttons
 LEFT  = 1 << 0
@@ -67,17 +154,33 @@ DOWN  = 1 << 2
 RUN   = 1 << 3
 JUMP  = 1 << 4
 
+# Must match platlab_core::ABI_VERSION; the structs above mirror that layout
+ABI_VERSION = 2
+
 lib = C.CDLL(LIB_PATH)
 
+lib.core_abi_version.argtypes = []
+lib.core_abi_version.restype = C.c_uint
+if lib.core_abi_version() != ABI_VERSION:
+    raise RuntimeError(
+        f"platlab_ffi ABI v{lib.core_abi_version()} does not match core.py (v{ABI_VERSION}); rebuild or update the bindings"
+    )
+
 lib.core_default_params.argtypes = [C.POINTER(Params)]
 lib.core_default_params.restype = None
 
 lib.core_init_state.argtypes = [C.POINTER(State), C.c_float, C.c_float, C.c_float, C.c_float]
 lib.core_init_state.restype = None
 
+lib.core_init_state_v.argtypes = [C.POINTER(State), C.c_float, C.c_float, C.c_float, C.c_float, C.c_float, C.c_float]
+lib.core_init_state_v.restype = None
+
 lib.core_step.argtypes = [C.POINTER(Params), C.POINTER(Rect), C.c_size_t, C.POINTER(State), C.c_ubyte]
 lib.core_step.restype = Events
 
+lib.core_step_input.argtypes = [C.POINTER(Params), C.POINTER(Rect), C.c_size_t, C.POINTER(State), C.POINTER(Input)]
+lib.core_step_input.restype = Events
+
 def default_params() -> Params:
     p = Params()
     lib.core_default_params(C.byref(p))
@@ -88,6 +191,15 @@ def init_state(x: float, y: float, w: float, h: float) -> State:
     lib.core_init_state(C.byref(s), x, y, w, h)
     return s
 
+def init_state_v(x: float, y: float, w: float, h: float, vx: float, vy: float) -> State:
+    s = State()
+    lib.core_init_state_v(C.byref(s), x, y, w, h, vx, vy)
+    return s
+
 def step(p: Params, world: list[Rect], s: State, input_bits: int) -> Events:
     arr = (Rect * len(world))(*world)
     return lib.core_step(C.byref(p), arr, len(world), C.byref(s), input_bits)
+
+def step_input(p: Params, world: list[Rect], s: State, inp: Input) -> Events:
+    arr = (Rect * len(world))(*world)
+    return lib.core_step_input(C.byref(p), arr, len(world), C.byref(s), C.byref(inp))
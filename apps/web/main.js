@@ -130,7 +130,7 @@ const PARAM_SPECS = [
   ["max_step_px", 1.0, 20.0, 0.5, (v) => v.toFixed(1)],
 
   ["show_debug", 0.0, 1.0, 1.0, (v) => v.toFixed(0)],
-  ["world_wrap_mode", 1.0, 2.0, 1.0, (v) => v.toFixed(0)],
+  ["world_wrap_mode", 1.0, 3.0, 1.0, (v) => v.toFixed(0)],
 ];
 
 const CORE_PARAM_KEYS = [
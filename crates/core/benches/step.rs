@@ -0,0 +1,89 @@
+//! Headless `step` benchmark.
+//!
+//! Runs the `parity_trace` input pattern (RIGHT for 120 frames, JUMP on frame 10,
+//! then idle to 180) against small, medium and large worlds and reports ns/step.
+//!
+//! ```text
+//! cargo bench -p platlab_core --bench step
+//! ```
+
+use std::hint::black_box;
+use std::time::Instant;
+
+use platlab_core::{step, Buttons, Params, Rect, State};
+
+const FRAMES: usize = 180;
+
+fn parity_inputs() -> Vec<u8> {
+    (0..FRAMES)
+        .map(|frame| {
+            let mut buttons = Buttons::empty();
+            if frame < 120 {
+                buttons |= Buttons::RIGHT;
+            }
+            if frame == 10 {
+                buttons |= Buttons::JUMP;
+            }
+            buttons.bits()
+        })
+        .collect()
+}
+
+/// Ground strip plus `count - 1` floating ledges laid out on a fixed grid above
+/// the player's path, so every frame has to consider the whole world.
+fn make_world(count: usize) -> Vec<Rect> {
+    let mut world = vec![Rect { x: 0.0, y: 480.0, w: 960.0, h: 60.0 }];
+    let cols = 24;
+    for i in 0..count.saturating_sub(1) {
+        let col = (i % cols) as f32;
+        let row = (i / cols) as f32;
+        world.push(Rect {
+            x: col * 40.0,
+            y: 300.0 - row * 30.0,
+            w: 32.0,
+            h: 8.0,
+        });
+    }
+    world
+}
+
+fn spawn() -> State {
+    State {
+        x: 80.0,
+        y: 480.0 - 44.0,
+        w: 28.0,
+        h: 44.0,
+        ..State::default()
+    }
+}
+
+fn bench(name: &str, params: &Params, world: &[Rect], inputs: &[u8], iterations: usize) {
+    let start = Instant::now();
+    for _ in 0..iterations {
+        let mut state = spawn();
+        for &bits in inputs {
+            let buttons = Buttons::from_bits_truncate(bits);
+            black_box(step(params, black_box(world), &mut state, buttons));
+        }
+        black_box(state);
+    }
+    let elapsed = start.elapsed();
+    let steps = (iterations * inputs.len()) as f64;
+    println!(
+        "{name:<8} rects={:<6} {:>10.1} ns/step",
+        world.len(),
+        elapsed.as_nanos() as f64 / steps
+    );
+}
+
+fn main() {
+    let params = Params {
+        world_w: 960.0,
+        ..Params::default()
+    };
+    let inputs = parity_inputs();
+
+    bench("small", &params, &make_world(1), &inputs, 2000);
+    bench("medium", &params, &make_world(300), &inputs, 200);
+    bench("large", &params, &make_world(3000), &inputs, 20);
+}
@@ -1,8 +1,10 @@
 use platlab_core::{step, Buttons, Params, Rect, State};
 
 fn main() {
-    let mut params = Params::default();
-    params.world_w = 960.0;
+    let params = Params {
+        world_w: 960.0,
+        ..Params::default()
+    };
 
     let world = [Rect {
         x: 0.0,
@@ -0,0 +1,103 @@
+//! Animation-state classification from physics output.
+//!
+//! Hosts should use this instead of re-deriving thresholds from raw fields.
+
+use crate::{Events, Params, State};
+
+/// Horizontal speed (px/s) below which a grounded player counts as standing still.
+pub const IDLE_SPEED: f32 = 1.0;
+
+#[repr(u8)]
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum AnimState {
+    Idle = 0,
+    Walk = 1,
+    /// Grounded and faster than `ground_max_speed`, which only RUN can reach.
+    Run = 2,
+    /// Jumped this frame or still rising.
+    Jump = 3,
+    Fall = 4,
+    /// Touched down this frame.
+    Land = 5,
+}
+
+/// Classifies the frame that produced `state`/`events`.
+///
+/// Priority: `Land` (landed this frame) > `Jump` (jumped this frame, or
+/// airborne with `vy < 0`) > `Fall` (airborne) > grounded `Run`/`Walk`/`Idle`.
+pub fn anim_state(params: &Params, state: &State, events: &Events) -> AnimState {
+    if events.landed != 0 {
+        return AnimState::Land;
+    }
+    if events.jumped != 0 {
+        return AnimState::Jump;
+    }
+    if state.grounded == 0 {
+        return if state.vy < 0.0 { AnimState::Jump } else { AnimState::Fall };
+    }
+
+    let speed = state.vx.abs();
+    if speed < IDLE_SPEED {
+        AnimState::Idle
+    } else if speed > params.ground_max_speed {
+        AnimState::Run
+    } else {
+        AnimState::Walk
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{anim_state, AnimState};
+    use crate::{step, Buttons, Params, Rect, State};
+
+    #[test]
+    fn classifies_main_transitions() {
+        let params = Params {
+            world_wrap_mode: 0.0,
+            ..Params::default()
+        };
+        let world = [Rect { x: 0.0, y: 480.0, w: 960.0, h: 60.0 }];
+        let mut state = State {
+            x: 100.0,
+            y: 480.0 - 44.0,
+            w: 28.0,
+            h: 44.0,
+            grounded: 1,
+            ..State::default()
+        };
+        let frame = |buttons: Buttons, state: &mut State| {
+            let ev = step(&params, &world, state, buttons);
+            anim_state(&params, state, &ev)
+        };
+
+        assert_eq!(frame(Buttons::empty(), &mut state), AnimState::Idle);
+        assert_eq!(frame(Buttons::RIGHT, &mut state), AnimState::Walk);
+
+        let mut top = AnimState::Walk;
+        for _ in 0..60 {
+            top = frame(Buttons::RIGHT | Buttons::RUN, &mut state);
+        }
+        assert_eq!(top, AnimState::Run);
+
+        assert_eq!(frame(Buttons::JUMP, &mut state), AnimState::Jump);
+        assert_eq!(frame(Buttons::JUMP, &mut state), AnimState::Jump);
+
+        let mut seen = Vec::new();
+        for _ in 0..120 {
+            let a = frame(Buttons::JUMP, &mut state);
+            if seen.last() != Some(&a) {
+                seen.push(a);
+            }
+            if a == AnimState::Land {
+                break;
+            }
+        }
+        assert_eq!(seen, [AnimState::Jump, AnimState::Fall, AnimState::Land]);
+
+        for _ in 0..60 {
+            frame(Buttons::empty(), &mut state);
+        }
+        assert_eq!(frame(Buttons::empty(), &mut state), AnimState::Idle);
+    }
+}
@@ -1,8 +1,18 @@
 use std::fs;
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
 
+use platlab_core::replay::{read_replay_bin, Replay};
 use platlab_core::{step, Buttons, Params, Rect, State};
 
+const USAGE: &str =
+    "usage: cargo run -p platlab_core --bin replay -- [--format json|bin] [--expected <trace.csv>] <replay>";
+
+/// Absolute tolerance when comparing numeric trace fields against `--expected`,
+/// so traces printed by other ports with different float formatting still match.
+const EXPECTED_EPS: f64 = 1e-3;
+
+const TRACE_HEADER: &str = "frame,x,y,vx,vy,grounded";
+
 fn section<'a>(src: &'a str, key: &str, open: char, close: char) -> &'a str {
     let k = format!("\"{key}\"");
     let ki = src.find(&k).unwrap_or_else(|| panic!("missing key: {key}"));
@@ -85,15 +95,50 @@ fn parse_world(src: &str) -> Vec<Rect> {
     out
 }
 
+/// Inputs may be raw bits (`18`) or quoted symbolic `Buttons` (`"RIGHT|JUMP"`, `"R J"`).
 fn parse_inputs(src: &str) -> Vec<u8> {
     let arr = section(src, "inputs", '[', ']');
     arr.split(',')
         .map(str::trim)
         .filter(|s| !s.is_empty())
-        .map(|s| s.parse::<u8>().expect("invalid input bit"))
+        .map(parse_symbol)
         .collect()
 }
 
+/// String value of a top-level `"key": "value"` pair, if present.
+fn string<'a>(src: &'a str, key: &str) -> Option<&'a str> {
+    let k = format!("\"{key}\"");
+    let rest = &src[src.find(&k)? + k.len()..];
+    let rest = rest[rest.find(':')? + 1..].trim_start().strip_prefix('"')?;
+    Some(&rest[..rest.find('"')?])
+}
+
+fn parse_symbol(s: &str) -> u8 {
+    s.trim_matches('"').parse::<Buttons>().unwrap_or_else(|e| panic!("{e}")).bits()
+}
+
+/// External input log: one frame per line as bits or symbols (`18`, `RIGHT|JUMP`),
+/// or a held run as `frames:bits` (`120:R J`). Blank lines and `#` comments are skipped.
+fn parse_input_log(src: &str) -> Vec<u8> {
+    let mut out = Vec::new();
+    for line in src.lines().map(str::trim) {
+        if line.is_empty() || line.starts_with('#') {
+            continue;
+        }
+        match line.split_once(':') {
+            Some((frames, bits)) => {
+                let frames: usize = frames
+                    .trim()
+                    .parse()
+                    .unwrap_or_else(|_| panic!("invalid run length in input log: {line}"));
+                out.extend(std::iter::repeat_n(parse_symbol(bits.trim()), frames));
+            }
+            None => out.push(parse_symbol(line)),
+        }
+    }
+    out
+}
+
 fn parse_params(src: &str) -> Params {
     let p = section(src, "params", '{', '}');
     Params {
@@ -102,22 +147,53 @@ fn parse_params(src: &str) -> Params {
         ground_decel: number(p, "ground_decel", Some(2200.0)),
         ground_friction: number(p, "ground_friction", Some(2600.0)),
         run_multiplier: number(p, "run_multiplier", Some(1.35)),
+        run_ramp_time: number(p, "run_ramp_time", Some(0.0)),
         air_max_speed: number(p, "air_max_speed", Some(220.0)),
         air_accel: number(p, "air_accel", Some(1200.0)),
         air_decel: number(p, "air_decel", Some(900.0)),
         air_drag: number(p, "air_drag", Some(0.0)),
+        air_max_speed_falling: number(p, "air_max_speed_falling", Some(-1.0)),
+        air_accel_falling: number(p, "air_accel_falling", Some(-1.0)),
         gravity_up: number(p, "gravity_up", Some(1500.0)),
         gravity_down: number(p, "gravity_down", Some(2300.0)),
         terminal_velocity: number(p, "terminal_velocity", Some(1200.0)),
+        max_rise_speed: number(p, "max_rise_speed", Some(5000.0)),
         fast_fall_multiplier: number(p, "fast_fall_multiplier", Some(1.35)),
+        wall_proximity_gravity_scale: number(p, "wall_proximity_gravity_scale", Some(1.0)),
+        auto_fast_fall_after: number(p, "auto_fast_fall_after", Some(0.0)),
+        gravity_blend: number(p, "gravity_blend", Some(0.0)),
+        integration_mode: number(p, "integration_mode", Some(0.0)),
         jump_velocity: number(p, "jump_velocity", Some(520.0)),
         jump_cut_multiplier: number(p, "jump_cut_multiplier", Some(0.45)),
+        min_jump_velocity: number(p, "min_jump_velocity", Some(0.0)),
+        jump_cut_gravity_boost: number(p, "jump_cut_gravity_boost", Some(1.0)),
+        jump_cut_boost_frames: number(p, "jump_cut_boost_frames", Some(12.0)),
         coyote_time: number(p, "coyote_time", Some(0.085)),
         jump_buffer: number(p, "jump_buffer", Some(0.1)),
+        coyote_frames: number(p, "coyote_frames", Some(0.0)),
+        jump_buffer_frames: number(p, "jump_buffer_frames", Some(0.0)),
+        grounded_grace_frames: number(p, "grounded_grace_frames", Some(0.0)),
+        auto_bounce: number(p, "auto_bounce", Some(0.0)),
         snap_to_ground: number(p, "snap_to_ground", Some(6.0)),
+        foot_width_fraction: number(p, "foot_width_fraction", Some(1.0)),
         max_step_px: number(p, "max_step_px", Some(6.0)),
+        max_step_px_x: number(p, "max_step_px_x", Some(0.0)),
+        max_step_px_y: number(p, "max_step_px_y", Some(0.0)),
+        max_substeps: number(p, "max_substeps", Some(64.0)),
+        min_substeps: number(p, "min_substeps", Some(1.0)),
+        min_max_step: number(p, "min_max_step", Some(1.0)),
+        jump_snap_suppress_frames: number(p, "jump_snap_suppress_frames", Some(2.0)),
         world_w: number(p, "world_w", Some(960.0)),
+        world_h: number(p, "world_h", Some(0.0)),
         world_wrap_mode: number(p, "world_wrap_mode", Some(1.0)),
+        world_bounce_restitution: number(p, "world_bounce_restitution", Some(0.5)),
+        bonk_restitution: number(p, "bonk_restitution", Some(0.0)),
+        max_fall_distance: number(p, "max_fall_distance", Some(0.0)),
+        input_deadzone: number(p, "input_deadzone", Some(0.0)),
+        input_smoothing: number(p, "input_smoothing", Some(0.0)),
+        freeze_axes: number(p, "freeze_axes", Some(0.0)),
+        noclip: number(p, "noclip", Some(0.0)),
+        disabled_events: number(p, "disabled_events", Some(0.0)),
     }
 }
 
@@ -134,28 +210,188 @@ fn parse_state(src: &str) -> State {
         coyote: number(s, "coyote", Some(0.0)),
         jump_buffer: number(s, "jump_buffer", Some(0.0)),
         jump_was_down: integer(s, "jump_was_down", Some(0)) as u8,
+        snap_suppress: integer(s, "snap_suppress", Some(0)) as u8,
+        run_ramp: number(s, "run_ramp", Some(0.0)),
+        hitbox_offset_x: number(s, "hitbox_offset_x", Some(0.0)),
+        hitbox_offset_y: number(s, "hitbox_offset_y", Some(0.0)),
+        hitbox_w: number(s, "hitbox_w", Some(0.0)),
+        hitbox_h: number(s, "hitbox_h", Some(0.0)),
+        just_landed: integer(s, "just_landed", Some(0)) as u8,
+        airtime_frames: integer(s, "airtime_frames", Some(0)) as u32,
+        max_airtime_frames: integer(s, "max_airtime_frames", Some(0)) as u32,
+        max_fall_speed: number(s, "max_fall_speed", Some(0.0)),
+        distance_x: number(s, "distance_x", Some(0.0)),
+        jump_launch_y: number(s, "jump_launch_y", Some(0.0)),
+        jump_active: integer(s, "jump_active", Some(0)) as u8,
+        last_jump_apex_height: number(s, "last_jump_apex_height", Some(0.0)),
+        pushing: integer(s, "pushing", Some(0)) as i8,
+        ground_normal_x: number(s, "ground_normal_x", Some(0.0)),
+        ground_normal_y: number(s, "ground_normal_y", Some(0.0)),
+        fall_distance: number(s, "fall_distance", Some(0.0)),
+        move_x_smoothed: number(s, "move_x_smoothed", Some(0.0)),
+        grounded_recently: integer(s, "grounded_recently", Some(0)) as u8,
+        jump_cut_timer: integer(s, "jump_cut_timer", Some(0)) as u8,
     }
 }
 
-fn main() {
-    let path = std::env::args()
-        .nth(1)
-        .map(PathBuf::from)
-        .expect("usage: cargo run -p platlab_core --bin replay -- <replay.json>");
+/// `"inputs_file"` (resolved relative to the replay) takes the place of an inline `"inputs"` array.
+fn load_json(path: &Path) -> Replay {
     let raw = fs::read_to_string(path).expect("failed to read replay json");
+    let inputs = match string(&raw, "inputs_file") {
+        Some(file) => {
+            let file = path.parent().unwrap_or(Path::new("")).join(file);
+            parse_input_log(&fs::read_to_string(&file).expect("failed to read inputs_file"))
+        }
+        None => parse_inputs(&raw),
+    };
+    Replay {
+        params: parse_params(&raw),
+        world: parse_world(&raw),
+        initial_state: parse_state(&raw),
+        inputs,
+    }
+}
+
+fn load_bin(path: &Path) -> Replay {
+    let file = fs::File::open(path).expect("failed to open binary replay");
+    read_replay_bin(std::io::BufReader::new(file)).expect("failed to read binary replay")
+}
+
+fn fields_match(a: &str, b: &str) -> bool {
+    let (fa, fb): (Vec<&str>, Vec<&str>) = (a.split(',').collect(), b.split(',').collect());
+    fa.len() == fb.len()
+        && fa.iter().zip(&fb).all(|(x, y)| match (x.trim().parse::<f64>(), y.trim().parse::<f64>()) {
+            (Ok(x), Ok(y)) => (x - y).abs() <= EXPECTED_EPS,
+            _ => x.trim() == y.trim(),
+        })
+}
+
+/// First row where `actual` and the `expected` CSV disagree, as
+/// `(frame, actual_row, expected_row)`. Header lines and blank lines in
+/// `expected` are skipped; a missing row on either side is a mismatch.
+fn first_mismatch(actual: &[String], expected: &str) -> Option<(usize, String, String)> {
+    let mut expected_rows = expected
+        .lines()
+        .map(str::trim)
+        .filter(|l| !l.is_empty() && !l.starts_with("frame"));
+    let mut actual_rows = actual.iter();
+    let mut frame = 0;
+    loop {
+        match (actual_rows.next(), expected_rows.next()) {
+            (None, None) => return None,
+            (Some(a), Some(e)) if fields_match(a, e) => frame += 1,
+            (a, e) => {
+                let show = |v: Option<&str>| v.unwrap_or("<missing>").to_string();
+                return Some((frame, show(a.map(String::as_str)), show(e)));
+            }
+        }
+    }
+}
+
+fn main() {
+    let mut format = String::from("json");
+    let mut expected = None;
+    let mut path = None;
+    let mut args = std::env::args().skip(1);
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--format" => format = args.next().expect(USAGE),
+            "--expected" => expected = Some(PathBuf::from(args.next().expect(USAGE))),
+            _ => path = Some(PathBuf::from(arg)),
+        }
+    }
+    let path = path.expect(USAGE);
 
-    let params = parse_params(&raw);
-    let world = parse_world(&raw);
-    let mut state = parse_state(&raw);
-    let inputs = parse_inputs(&raw);
+    let Replay { params, world, initial_state, inputs } = match format.as_str() {
+        "json" => load_json(&path),
+        "bin" => load_bin(&path),
+        other => panic!("unknown replay format: {other}\n{USAGE}"),
+    };
+    let mut state = initial_state;
 
-    println!("frame,x,y,vx,vy,grounded");
+    let mut rows = Vec::with_capacity(inputs.len());
     for (frame, bits) in inputs.iter().enumerate() {
         let buttons = Buttons::from_bits_truncate(*bits);
         let _ = step(&params, &world, &mut state, buttons);
-        println!(
+        rows.push(format!(
             "{},{},{},{},{},{}",
             frame, state.x, state.y, state.vx, state.vy, state.grounded
-        );
+        ));
+    }
+
+    let Some(expected) = expected else {
+        println!("{TRACE_HEADER}");
+        for row in &rows {
+            println!("{row}");
+        }
+        return;
+    };
+
+    let raw = fs::read_to_string(&expected).expect("failed to read expected trace");
+    match first_mismatch(&rows, &raw) {
+        None => println!("trace matches {} ({} frames)", expected.display(), rows.len()),
+        Some((frame, actual, wanted)) => {
+            eprintln!("first mismatch at frame {frame}");
+            eprintln!("  {TRACE_HEADER}");
+            eprintln!("  actual:   {actual}");
+            eprintln!("  expected: {wanted}");
+            std::process::exit(1);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{first_mismatch, load_json, parse_input_log, parse_inputs};
+
+    fn rows() -> Vec<String> {
+        vec!["0,80,436,30,0,1".into(), "1,80.5,436,60,0,1".into(), "2,81,436,90,0,1".into()]
+    }
+
+    #[test]
+    fn matching_trace_passes_with_header_and_float_noise() {
+        let expected = "frame,x,y,vx,vy,grounded\n0,80.0,436,30.0002,0,1\n1,80.5,436,60,0,1\n2,81,436,90,-0,1\n";
+        assert_eq!(first_mismatch(&rows(), expected), None);
+    }
+
+    #[test]
+    fn reports_first_mismatched_frame() {
+        let expected = "frame,x,y,vx,vy,grounded\n0,80,436,30,0,1\n1,80.5,437,60,0,1\n2,99,436,90,0,1\n";
+        let (frame, actual, wanted) = first_mismatch(&rows(), expected).unwrap();
+        assert_eq!(frame, 1);
+        assert_eq!(actual, "1,80.5,436,60,0,1");
+        assert_eq!(wanted, "1,80.5,437,60,0,1");
+    }
+
+    #[test]
+    fn length_difference_is_a_mismatch() {
+        let expected = "0,80,436,30,0,1\n1,80.5,436,60,0,1\n";
+        let (frame, _, wanted) = first_mismatch(&rows(), expected).unwrap();
+        assert_eq!((frame, wanted.as_str()), (2, "<missing>"));
+    }
+
+    #[test]
+    fn replays_from_external_rle_input_file() {
+        let dir = std::env::temp_dir().join(format!("platlab_replay_{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        std::fs::write(dir.join("session.inputs"), "# warmup\n3:0\n2:RIGHT|JUMP\n\n18\n1:L\n").unwrap();
+        let spec = r#"{
+            "params": {},
+            "world": [{"x": 0, "y": 480, "w": 960, "h": 60}],
+            "initial_state": {"x": 80, "y": 436, "w": 28, "h": 44},
+            "inputs_file": "session.inputs"
+        }"#;
+        std::fs::write(dir.join("replay.json"), spec).unwrap();
+
+        let replay = load_json(&dir.join("replay.json"));
+        std::fs::remove_dir_all(&dir).unwrap();
+        assert_eq!(replay.inputs, [0, 0, 0, 18, 18, 18, 1]);
+        assert_eq!(replay.inputs, parse_inputs(r#""inputs": [0, 0, 0, "R J", "R|J", 18, "L"]"#));
+        assert_eq!(replay.world.len(), 1);
+    }
+
+    #[test]
+    fn input_log_run_of_zero_frames_is_empty() {
+        assert_eq!(parse_input_log("0:JUMP\n2:0"), [0, 0]);
     }
 }
@@ -1,8 +1,46 @@
 #![allow(clippy::many_single_char_names)]
 
+pub mod anim;
+pub mod obb;
+pub mod replay;
+pub mod world;
+#[cfg(any(test, feature = "test-support"))]
+pub mod testing;
+
+pub use anim::{anim_state, AnimState};
+pub use obb::ObbRect;
+pub use world::{CollisionWorld, WorldBuilder};
+use obb::aabb_obb_mtv;
+use world::any_intersect;
+
+/// Version of the `#[repr(C)]` `Params` / `State` / `Events` / `Input` layouts that
+/// FFI and ctypes hosts mirror (`core_abi_version`). v1 was the original 21-field
+/// `Params`, 10-field `State` and 3-field `Events`. v2 appends fields to all of them
+/// and adds `Input`: the v1 prefixes keep their offsets, but the structs are larger,
+/// so v1 hosts must rebuild their mirrors. Bumped whenever a layout changes.
+pub const ABI_VERSION: u32 = 2;
+
 pub const HZ: f32 = 60.0;
 pub const DT: f32 = 1.0 / HZ;
 
+// Integration clamps applied by `step`.
+/// Default `Params::max_rise_speed` (px/s).
+pub const MAX_UPWARD_SPEED: f32 = 5000.0;
+/// Default `Params::min_max_step`: lower bound on `max_step_px` and its
+/// per-axis overrides (px per substep).
+pub const MIN_STEP_PX: f32 = 1.0;
+/// Default `Params::min_substeps`: fewest collision substeps run per frame.
+pub const MIN_SUBSTEPS: f32 = 1.0;
+/// Smallest `Params::min_max_step` honoured, so the substep count stays finite.
+const MIN_STEP_PX_FLOOR: f32 = 0.01;
+/// Horizontal gap (px) within which `Params::wall_proximity_gravity_scale` applies.
+pub const WALL_PROXIMITY_PX: f32 = 4.0;
+/// Lower bound applied to `Params::world_w` in wrap modes 2 and 3 (mode 1
+/// follows the pygame sandbox, which has none).
+pub const MIN_WORLD_W: f32 = 1.0;
+/// Height of [`world_bounds`] when `Params::world_h` is 0 (unbounded), centred on `y = 0`.
+pub const UNBOUNDED_WORLD_H: f32 = 1.0e9;
+
 #[repr(C)]
 #[derive(Copy, Clone, Debug, Default)]
 pub struct Rect {
@@ -12,9 +50,42 @@ pub struct Rect {
     pub h: f32,
 }
 
+/// True when the open interiors of `a` and `b` overlap. Degenerate rects
+/// (`w <= 0` or `h <= 0`, e.g. tile seams) never intersect anything.
 #[inline]
 pub fn rects_intersect(a: &Rect, b: &Rect) -> bool {
-    a.x < b.x + b.w && a.x + a.w > b.x && a.y < b.y + b.h && a.y + a.h > b.y
+    a.w > 0.0 && a.h > 0.0 && b.w > 0.0 && b.h > 0.0
+        && a.x < b.x + b.w && a.x + a.w > b.x && a.y < b.y + b.h && a.y + a.h > b.y
+}
+
+/// Minimum translation vector `(dx, dy)` that moves `a` out of `b`, or `None`
+/// when they don't overlap (edges exactly touching count as not overlapping).
+///
+/// Only one component is non-zero: the axis with the smaller overlap wins
+/// (Y on a tie), using the shorter way out of `b` along that axis.
+pub fn penetration(a: &Rect, b: &Rect) -> Option<(f32, f32)> {
+    if !rects_intersect(a, b) {
+        return None;
+    }
+    let push_left = b.x - (a.x + a.w);
+    let push_right = (b.x + b.w) - a.x;
+    let push_up = b.y - (a.y + a.h);
+    let push_down = (b.y + b.h) - a.y;
+
+    let dx = if push_right < -push_left { push_right } else { push_left };
+    let dy = if push_down < -push_up { push_down } else { push_up };
+    if dx.abs() < dy.abs() { Some((dx, 0.0)) } else { Some((0.0, dy)) }
+}
+
+/// Smallest rect enclosing `rect` both where it starts and after moving by
+/// `(dx, dy)`: the area a broadphase must search for one frame's motion.
+pub fn swept_aabb(rect: &Rect, dx: f32, dy: f32) -> Rect {
+    Rect {
+        x: rect.x + dx.min(0.0),
+        y: rect.y + dy.min(0.0),
+        w: rect.w + dx.abs(),
+        h: rect.h + dy.abs(),
+    }
 }
 
 #[repr(C)]
@@ -51,8 +122,90 @@ pub struct Params {
 
     // World
     pub world_w: f32,
-    // 0 = off, 1 = edge-wrap (pygame legacy), 2 = center-wrap torus (web legacy)
+    // 0 = off, 1 = edge-wrap (pygame legacy, wraps even into solids), 2 = center-wrap
+    // torus (web legacy), 3 = bounce off left/right edges
     pub world_wrap_mode: f32,
+
+    // Added after ABI v1 (see ABI_VERSION), oldest first. New fields go at the end so
+    // the offsets above never move
+
+    // Fraction of vx kept (and reflected) when bouncing off an edge in wrap mode 3
+    pub world_bounce_restitution: f32,
+    // 0 = hard up/down split on sign of vy, 1 = linear blend across [-jump_velocity, terminal_velocity]
+    pub gravity_blend: f32,
+    // Frames after a jump (beyond the jump frame itself) during which ground-snap is skipped
+    pub jump_snap_suppress_frames: f32,
+    // Air control while falling (vy > 0); negative = same as air_max_speed / air_accel
+    pub air_max_speed_falling: f32,
+    pub air_accel_falling: f32,
+    // > 0 = fly freely with the movement inputs (JUMP = up, DOWN = down), ignoring
+    // gravity, collision and ground-snap; world wrap still applies
+    pub noclip: f32,
+    // Upper bound on substeps per frame; beyond it velocity is clamped to max_substeps * max_step_px
+    // (per axis) per frame
+    pub max_substeps: f32,
+    // Seconds to ramp between walk and run speed while RUN is held/released (0 = instant)
+    pub run_ramp_time: f32,
+    // `EventMask` bits (as an integer) of events `step` skips reporting; physics is unaffected
+    pub disabled_events: f32,
+    // 0 = semi-implicit Euler (gravity, then move), 1 = explicit Euler (move with the
+    // pre-gravity vy, legacy): falling from rest, explicit trails by gravity*DT^2*n px after n frames
+    pub integration_mode: f32,
+    // Fastest upward speed (px/s); vy is clamped to -max_rise_speed
+    pub max_rise_speed: f32,
+    // Downward distance (px) fallen since leaving the ground that fires `fell_too_far`; 0 = off
+    pub max_fall_distance: f32,
+    // Integer frame-count alternatives to coyote_time / jump_buffer (> 0 overrides the
+    // seconds value): the timer counts down 1 per step and the jump check sees exactly
+    // this many frames (walk-off frames for coyote, press frame onward for the buffer)
+    pub coyote_frames: f32,
+    pub jump_buffer_frames: f32,
+    // Axis locks as bits (1 = x, 2 = y): a frozen axis has its velocity zeroed and
+    // position held (no integration, collision push or ground-snap pull on it)
+    pub freeze_axes: f32,
+    // Floor (px/s) on the upward speed a jump cut leaves, so a one-frame tap still
+    // reaches a fixed minimum hop; 0 = cut to jump_velocity * jump_cut_multiplier only
+    pub min_jump_velocity: f32,
+    // step_input: |move_x| below this is treated as 0; the rest of the range is rescaled to (0, 1]
+    pub input_deadzone: f32,
+    // Fraction [0, 1) of last frame's smoothed move_x kept each frame (0 = raw)
+    pub input_smoothing: f32,
+    // Gravity multiplier while airborne, falling and within WALL_PROXIMITY_PX of a solid
+    // rect on either side (wall-grab assist; 1 = off)
+    pub wall_proximity_gravity_scale: f32,
+    // Accessibility: once airtime_frames exceeds this many frames, descending uses
+    // fast-fall gravity as if DOWN were held (0 = off)
+    pub auto_fast_fall_after: f32,
+    // Extra jump grace (frames) after any grounded frame, counted in State::grounded_recently;
+    // unlike coyote it re-arms even on a one-frame touchdown, covering ground that drops away
+    // (descents, platforms falling out from under the player); 0 = off
+    pub grounded_grace_frames: f32,
+    // Per-axis substep size overrides (px); 0 = use max_step_px. The substep count is
+    // driven by whichever axis needs more, so a small max_step_px_y refines landings on
+    // thin platforms while level running keeps coarse substeps
+    pub max_step_px_x: f32,
+    pub max_step_px_y: f32,
+    // World height (px) for world_bounds; 0 = unbounded vertically. step ignores it
+    pub world_h: f32,
+    // Width of the ground probe as a fraction of the hitbox, centred under it; only ground
+    // under that foot counts for grounding and ground-snap (walls still use the full rect).
+    // 1 = full width
+    pub foot_width_fraction: f32,
+    // Floors for the substep math: every frame runs at least min_substeps substeps, and
+    // max_step_px (and its per-axis overrides) is raised to at least min_max_step px.
+    // Defaults are MIN_SUBSTEPS / MIN_STEP_PX; values below 1 substep / 0.01 px are raised
+    pub min_substeps: f32,
+    pub min_max_step: f32,
+    // > 0 = JUMP held through a landing jumps again on the first grounded frame, so
+    // holding JUMP bunny-hops; 0 = each jump needs a fresh press (or the buffer)
+    pub auto_bounce: f32,
+    // Fraction of upward speed reflected downward when the head hits a ceiling rect;
+    // 0 = stop dead (the classic bonk)
+    pub bonk_restitution: f32,
+    // Gravity multiplier for the jump_cut_boost_frames frames after a jump cut, so short
+    // hops come down faster; 1 = no boost
+    pub jump_cut_gravity_boost: f32,
+    pub jump_cut_boost_frames: f32,
 }
 
 impl Default for Params {
@@ -63,33 +216,200 @@ impl Default for Params {
             ground_decel: 2200.0,
             ground_friction: 2600.0,
             run_multiplier: 1.35,
+            run_ramp_time: 0.0,
 
             air_max_speed: 220.0,
             air_accel: 1200.0,
             air_decel: 900.0,
             air_drag: 0.0,
+            air_max_speed_falling: -1.0,
+            air_accel_falling: -1.0,
 
             gravity_up: 1500.0,
             gravity_down: 2300.0,
             terminal_velocity: 1200.0,
+            max_rise_speed: MAX_UPWARD_SPEED,
             fast_fall_multiplier: 1.35,
+            wall_proximity_gravity_scale: 1.0,
+            auto_fast_fall_after: 0.0,
+            gravity_blend: 0.0,
+            integration_mode: 0.0,
 
             jump_velocity: 520.0,
             jump_cut_multiplier: 0.45,
+            min_jump_velocity: 0.0,
+            jump_cut_gravity_boost: 1.0,
+            jump_cut_boost_frames: 12.0,
             coyote_time: 0.085,
             jump_buffer: 0.100,
+            coyote_frames: 0.0,
+            jump_buffer_frames: 0.0,
+            grounded_grace_frames: 0.0,
+            auto_bounce: 0.0,
 
             snap_to_ground: 6.0,
+            foot_width_fraction: 1.0,
             max_step_px: 6.0,
+            max_step_px_x: 0.0,
+            max_step_px_y: 0.0,
+            max_substeps: 64.0,
+            min_substeps: MIN_SUBSTEPS,
+            min_max_step: MIN_STEP_PX,
+            jump_snap_suppress_frames: 2.0,
 
             world_w: 960.0,
+            world_h: 0.0,
             world_wrap_mode: 1.0,
+            world_bounce_restitution: 0.5,
+            bonk_restitution: 0.0,
+            max_fall_distance: 0.0,
+            input_deadzone: 0.0,
+            input_smoothing: 0.0,
+            freeze_axes: 0.0,
+
+            noclip: 0.0,
+            disabled_events: 0.0,
+        }
+    }
+}
+
+/// `validate` warns when `terminal_velocity` is within this fraction of `MAX_UPWARD_SPEED`.
+pub const TERMINAL_VELOCITY_WARN_FRACTION: f32 = 0.9;
+
+/// `Params` field names in declaration order (matches `Params::as_slice`).
+pub const PARAM_NAMES: &[&str] = &[
+    "ground_max_speed",
+    "ground_accel",
+    "ground_decel",
+    "ground_friction",
+    "run_multiplier",
+    "air_max_speed",
+    "air_accel",
+    "air_decel",
+    "air_drag",
+    "gravity_up",
+    "gravity_down",
+    "terminal_velocity",
+    "fast_fall_multiplier",
+    "jump_velocity",
+    "jump_cut_multiplier",
+    "coyote_time",
+    "jump_buffer",
+    "snap_to_ground",
+    "max_step_px",
+    "world_w",
+    "world_wrap_mode",
+    "world_bounce_restitution",
+    "gravity_blend",
+    "jump_snap_suppress_frames",
+    "air_max_speed_falling",
+    "air_accel_falling",
+    "noclip",
+    "max_substeps",
+    "run_ramp_time",
+    "disabled_events",
+    "integration_mode",
+    "max_rise_speed",
+    "max_fall_distance",
+    "coyote_frames",
+    "jump_buffer_frames",
+    "freeze_axes",
+    "min_jump_velocity",
+    "input_deadzone",
+    "input_smoothing",
+    "wall_proximity_gravity_scale",
+    "auto_fast_fall_after",
+    "grounded_grace_frames",
+    "max_step_px_x",
+    "max_step_px_y",
+    "world_h",
+    "foot_width_fraction",
+    "min_substeps",
+    "min_max_step",
+    "auto_bounce",
+    "bonk_restitution",
+    "jump_cut_gravity_boost",
+    "jump_cut_boost_frames",
+];
+
+const _: () = assert!(std::mem::size_of::<Params>() == PARAM_NAMES.len() * std::mem::size_of::<f32>());
+
+/// Suspicious parameter combinations reported by [`Params::validate`].
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum ParamsWarning {
+    /// A field is NaN or infinite.
+    NonFinite(&'static str),
+    /// `jump_velocity` exceeds `max_rise_speed` and will be clamped.
+    JumpVelocityAboveMaxUpwardSpeed,
+    /// `terminal_velocity` is at or near `MAX_UPWARD_SPEED`; substeps get expensive.
+    TerminalVelocityNearClamp,
+    /// `terminal_velocity <= 0` means the player can never fall.
+    TerminalVelocityNotPositive,
+    /// `max_step_px` (or a non-zero per-axis override) is below `min_max_step` and is silently raised.
+    MaxStepBelowMinimum,
+    /// `jump_cut_multiplier` outside `0..=1` makes releasing JUMP speed the jump up.
+    JumpCutMultiplierOutOfRange,
+    /// `world_wrap_mode` is not one of the known modes (0..=3).
+    UnknownWrapMode,
+}
+
+impl Params {
+    /// Field values in declaration order. Every field is an `f32` and the
+    /// struct is `#[repr(C)]`, so this is a view of the struct itself.
+    pub fn as_slice(&self) -> &[f32] {
+        // SAFETY: `Params` is `#[repr(C)]` with only `f32` fields (checked by the
+        // size assertion next to `PARAM_NAMES`), so it has no padding and `f32` alignment.
+        unsafe { std::slice::from_raw_parts(self as *const Params as *const f32, PARAM_NAMES.len()) }
+    }
+
+    /// Mutable counterpart of [`Params::as_slice`].
+    pub fn as_mut_slice(&mut self) -> &mut [f32] {
+        // SAFETY: see `as_slice`.
+        unsafe { std::slice::from_raw_parts_mut(self as *mut Params as *mut f32, PARAM_NAMES.len()) }
+    }
+
+    /// Every field paired with its name, in declaration order.
+    pub fn named_fields(&self) -> Vec<(&'static str, f32)> {
+        PARAM_NAMES.iter().copied().zip(self.as_slice().iter().copied()).collect()
+    }
+
+    /// Flags suspicious values and combinations. Never modifies the params;
+    /// `step` still runs with whatever the host provides.
+    pub fn validate(&self) -> Result<(), Vec<ParamsWarning>> {
+        let mut warnings = Vec::new();
+
+        for (name, value) in self.named_fields() {
+            if !value.is_finite() {
+                warnings.push(ParamsWarning::NonFinite(name));
+            }
+        }
+        if self.jump_velocity > self.max_rise_speed {
+            warnings.push(ParamsWarning::JumpVelocityAboveMaxUpwardSpeed);
         }
+        if self.terminal_velocity <= 0.0 {
+            warnings.push(ParamsWarning::TerminalVelocityNotPositive);
+        } else if self.terminal_velocity >= MAX_UPWARD_SPEED * TERMINAL_VELOCITY_WARN_FRACTION {
+            warnings.push(ParamsWarning::TerminalVelocityNearClamp);
+        }
+        let axis_below = |v: f32| v > 0.0 && v < self.min_max_step;
+        if self.max_step_px < self.min_max_step || axis_below(self.max_step_px_x) || axis_below(self.max_step_px_y) {
+            warnings.push(ParamsWarning::MaxStepBelowMinimum);
+        }
+        if !(0.0..=1.0).contains(&self.jump_cut_multiplier) {
+            warnings.push(ParamsWarning::JumpCutMultiplierOutOfRange);
+        }
+        let wrap = self.world_wrap_mode.round();
+        if !(0.0..=3.0).contains(&wrap) || wrap != self.world_wrap_mode {
+            warnings.push(ParamsWarning::UnknownWrapMode);
+        }
+
+        if warnings.is_empty() { Ok(()) } else { Err(warnings) }
     }
 }
 
 bitflags::bitflags! {
     #[repr(transparent)]
+    #[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
     pub struct Buttons: u8 {
         const LEFT  = 1 << 0;
         const RIGHT = 1 << 1;
@@ -99,6 +419,96 @@ bitflags::bitflags! {
     }
 }
 
+/// Flag names and their one-letter shorthands, in bit order.
+const BUTTON_NAMES: [(Buttons, &str, char); 5] = [
+    (Buttons::LEFT, "LEFT", 'L'),
+    (Buttons::RIGHT, "RIGHT", 'R'),
+    (Buttons::DOWN, "DOWN", 'D'),
+    (Buttons::RUN, "RUN", 'B'),
+    (Buttons::JUMP, "JUMP", 'J'),
+];
+
+/// Prints active flags as `LEFT|JUMP`, or `NONE` for the empty set.
+impl std::fmt::Display for Buttons {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let mut first = true;
+        for (flag, name, _) in BUTTON_NAMES {
+            if self.contains(flag) {
+                if !first {
+                    f.write_str("|")?;
+                }
+                f.write_str(name)?;
+                first = false;
+            }
+        }
+        if first {
+            f.write_str("NONE")?;
+        }
+        Ok(())
+    }
+}
+
+/// Error returned when a string isn't a recognised `Buttons` form.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct ParseButtonsError(pub String);
+
+impl std::fmt::Display for ParseButtonsError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "invalid buttons: {:?}", self.0)
+    }
+}
+
+impl std::error::Error for ParseButtonsError {}
+
+/// Accepts raw bits (`"18"`), flag names separated by `|`, `+`, or whitespace
+/// (`"RIGHT|JUMP"`), or one-letter shorthands (`"LR J"`: L=LEFT, R=RIGHT,
+/// D=DOWN, B=RUN, J=JUMP). `""`, `"-"`, and `"NONE"` are the empty set.
+/// Names are case-insensitive; unknown bits in the numeric form are dropped.
+impl std::str::FromStr for Buttons {
+    type Err = ParseButtonsError;
+
+    fn from_str(src: &str) -> Result<Self, Self::Err> {
+        let src = src.trim();
+        if let Ok(bits) = src.parse::<u8>() {
+            return Ok(Buttons::from_bits_truncate(bits));
+        }
+
+        let mut out = Buttons::empty();
+        for token in src.split(|c: char| c == '|' || c == '+' || c.is_whitespace()) {
+            if token.is_empty() || token == "-" || token.eq_ignore_ascii_case("NONE") {
+                continue;
+            }
+            if let Some((flag, _, _)) = BUTTON_NAMES.iter().find(|(_, name, _)| token.eq_ignore_ascii_case(name)) {
+                out |= *flag;
+                continue;
+            }
+            for ch in token.chars() {
+                let ch = ch.to_ascii_uppercase();
+                match BUTTON_NAMES.iter().find(|(_, _, short)| *short == ch) {
+                    Some((flag, _, _)) => out |= *flag,
+                    None => return Err(ParseButtonsError(src.to_string())),
+                }
+            }
+        }
+        Ok(out)
+    }
+}
+
+/// Per-frame input for hosts that need more than the button bits (see
+/// [`step_input`]). New fields are appended so the C ABI stays compatible.
+#[repr(C)]
+#[derive(Copy, Clone, Debug, Default)]
+pub struct Input {
+    /// Raw `Buttons` bits.
+    pub bits: u8,
+    /// Analog horizontal axis in [-1, 1]. Non-zero overrides LEFT/RIGHT and
+    /// scales the max horizontal speed by `|move_x|`; 0 falls back to the bits.
+    pub move_x: f32,
+    /// Fraction of the frame [0, 1) at which JUMP went down. Reserved for
+    /// sub-frame jump timing; currently ignored by `step`.
+    pub jump_subframe: f32,
+}
+
 #[repr(C)]
 #[derive(Copy, Clone, Debug, Default)]
 pub struct State {
@@ -113,14 +523,275 @@ pub struct State {
     pub coyote: f32,
     pub jump_buffer: f32,
     pub jump_was_down: u8,
+    /// Frames left during which ground-snap is skipped after a jump.
+    pub snap_suppress: u8,
+    /// Walk-to-run blend in [0, 1], advanced by `Params::run_ramp_time`.
+    pub run_ramp: f32,
+    /// Collision box relative to `x`/`y` (the drawn sprite). `hitbox_w`/`hitbox_h`
+    /// <= 0 mean "same as `w`/`h`", so the defaults collide with the full sprite.
+    pub hitbox_offset_x: f32,
+    pub hitbox_offset_y: f32,
+    pub hitbox_w: f32,
+    pub hitbox_h: f32,
+    /// Set on the landing frame; while set, the next frame doesn't re-arm `coyote`
+    /// (so a one-frame brush against a ledge can't chain coyote windows).
+    pub just_landed: u8,
+    /// Frames airborne since last leaving the ground; reset to 0 on landing.
+    pub airtime_frames: u32,
+    /// Longest completed airtime (frames) since `reset_metrics`.
+    pub max_airtime_frames: u32,
+    /// Highest downward `vy` (px/s) reached since `reset_metrics`.
+    pub max_fall_speed: f32,
+    /// Total horizontal distance (px) moved by physics since `reset_metrics`; wrap teleports don't count.
+    pub distance_x: f32,
+    /// `y` at the most recent jump, and whether that jump is still airborne.
+    pub jump_launch_y: f32,
+    pub jump_active: u8,
+    /// Peak height (px above launch) of the current or most recent jump.
+    pub last_jump_apex_height: f32,
+    /// -1/+1 while grounded and holding toward a wall that fully blocked horizontal
+    /// movement this frame; 0 otherwise.
+    pub pushing: i8,
+    /// Unit surface normal under the player (`(0, -1)` on flat ground, tilted on
+    /// OBB slopes); `(0, 0)` while airborne.
+    pub ground_normal_x: f32,
+    pub ground_normal_y: f32,
+    /// Downward distance (px) fallen since last grounded (upward movement doesn't
+    /// subtract); reset to 0 on landing.
+    pub fall_distance: f32,
+    /// `step_input`'s smoothed analog axis (see `Params::input_smoothing`).
+    pub move_x_smoothed: f32,
+    /// Frames of `Params::grounded_grace_frames` left since the player was last grounded.
+    pub grounded_recently: u8,
+    /// Frames left of `Params::jump_cut_gravity_boost` after the last jump cut.
+    pub jump_cut_timer: u8,
+}
+
+impl State {
+    /// The rect used for collision and ground-snap (see `hitbox_*`).
+    pub fn hitbox(&self) -> Rect {
+        Rect {
+            x: self.x + self.hitbox_offset_x,
+            y: self.y + self.hitbox_offset_y,
+            w: if self.hitbox_w > 0.0 { self.hitbox_w } else { self.w },
+            h: if self.hitbox_h > 0.0 { self.hitbox_h } else { self.h },
+        }
+    }
+
+    /// Clears the scoring metrics (`airtime_frames`, `max_airtime_frames`,
+    /// `max_fall_speed`, `distance_x`) without touching the physics state.
+    pub fn reset_metrics(&mut self) {
+        self.airtime_frames = 0;
+        self.max_airtime_frames = 0;
+        self.max_fall_speed = 0.0;
+        self.distance_x = 0.0;
+    }
+}
+
+/// Float fields closer than this count as equal in [`diff_state`].
+pub const STATE_DIFF_EPSILON: f32 = 1e-4;
+
+/// Every `State` field as `(name, value)`, integers widened to `f32`.
+fn state_fields(s: &State) -> [(&'static str, f32); 31] {
+    // Exhaustive on purpose: a new `State` field must be added here too.
+    let State {
+        x, y, vx, vy, w, h, grounded, coyote, jump_buffer, jump_was_down, snap_suppress, run_ramp,
+        hitbox_offset_x, hitbox_offset_y, hitbox_w, hitbox_h, just_landed, airtime_frames,
+        max_airtime_frames, max_fall_speed, distance_x, jump_launch_y, jump_active,
+        last_jump_apex_height, pushing, ground_normal_x, ground_normal_y, fall_distance,
+        move_x_smoothed, grounded_recently, jump_cut_timer,
+    } = *s;
+    [
+        ("x", x),
+        ("y", y),
+        ("vx", vx),
+        ("vy", vy),
+        ("w", w),
+        ("h", h),
+        ("grounded", grounded as f32),
+        ("coyote", coyote),
+        ("jump_buffer", jump_buffer),
+        ("jump_was_down", jump_was_down as f32),
+        ("snap_suppress", snap_suppress as f32),
+        ("run_ramp", run_ramp),
+        ("hitbox_offset_x", hitbox_offset_x),
+        ("hitbox_offset_y", hitbox_offset_y),
+        ("hitbox_w", hitbox_w),
+        ("hitbox_h", hitbox_h),
+        ("just_landed", just_landed as f32),
+        ("airtime_frames", airtime_frames as f32),
+        ("max_airtime_frames", max_airtime_frames as f32),
+        ("max_fall_speed", max_fall_speed),
+        ("distance_x", distance_x),
+        ("jump_launch_y", jump_launch_y),
+        ("jump_active", jump_active as f32),
+        ("last_jump_apex_height", last_jump_apex_height),
+        ("pushing", pushing as f32),
+        ("ground_normal_x", ground_normal_x),
+        ("ground_normal_y", ground_normal_y),
+        ("fall_distance", fall_distance),
+        ("move_x_smoothed", move_x_smoothed),
+        ("grounded_recently", grounded_recently as f32),
+        ("jump_cut_timer", jump_cut_timer as f32),
+    ]
+}
+
+/// The `State` fields that differ between `a` and `b`, as `(name, a, b)` in
+/// declaration order. Floats within [`STATE_DIFF_EPSILON`] (or both NaN) match.
+pub fn diff_state(a: &State, b: &State) -> Vec<(&'static str, f32, f32)> {
+    state_fields(a)
+        .into_iter()
+        .zip(state_fields(b))
+        .filter(|&((_, x), (_, y))| !(x == y || (x - y).abs() <= STATE_DIFF_EPSILON || (x.is_nan() && y.is_nan())))
+        .map(|((name, x), (_, y))| (name, x, y))
+        .collect()
 }
 
 #[repr(C)]
-#[derive(Copy, Clone, Debug, Default)]
+#[derive(Copy, Clone, Debug)]
 pub struct Events {
     pub jumped: u8,
     pub landed: u8,
     pub bonked: u8,
+    /// Which path grounded the player this frame (a `GroundReason` as `u8`).
+    pub ground_reason: u8,
+    /// Set on frames where DOWN (or `auto_fast_fall_after`) applied
+    /// `fast_fall_multiplier` (only while descending).
+    pub fast_falling: u8,
+    /// Set when a non-finite velocity/position/timer was produced and reset this frame.
+    pub numeric_fault: u8,
+    /// Set on the frame the player goes from grounded to airborne (walk-off or jump).
+    pub left_ground: u8,
+    /// Set on the frame `State::fall_distance` first reaches `Params::max_fall_distance`.
+    pub fell_too_far: u8,
+    /// Set on the frame a buffered jump press runs out while airborne without jumping.
+    pub jump_wasted: u8,
+    /// On landing frames, when in the frame the ground contact happened, in (0, 1]:
+    /// the end of the substep that first touched ground over the substep count (1
+    /// for a ground-snap landing). 0 when `landed` is not set.
+    pub land_toi: f32,
+    /// Velocity (px/s) after input, gravity, jump and the clamps, just before the
+    /// collision substeps; compare with the final `State` velocity to see what
+    /// collision changed. Observational only.
+    pub vx_pre_collision: f32,
+    pub vy_pre_collision: f32,
+    /// Which path executed this frame's jump (a `JumpKind` as `u8`); `None` when
+    /// `jumped` is not set.
+    pub jump_kind: u8,
+    /// Index into the world of the ceiling rect behind `bonked`, and of the last
+    /// wall rect that stopped horizontal movement this frame; -1 when none.
+    pub bonk_index: i32,
+    pub wall_index: i32,
+}
+
+impl Default for Events {
+    /// No events: every flag and value 0, rect indices -1.
+    fn default() -> Self {
+        Self {
+            jumped: 0,
+            landed: 0,
+            bonked: 0,
+            ground_reason: 0,
+            fast_falling: 0,
+            numeric_fault: 0,
+            left_ground: 0,
+            fell_too_far: 0,
+            jump_wasted: 0,
+            land_toi: 0.0,
+            vx_pre_collision: 0.0,
+            vy_pre_collision: 0.0,
+            jump_kind: 0,
+            bonk_index: -1,
+            wall_index: -1,
+        }
+    }
+}
+
+impl Events {
+    /// The flag-style events that fired this frame, packed as [`EventMask`] bits
+    /// (`GROUND_REASON` = grounded by any path, `WALL` = `wall_index` set).
+    /// `numeric_fault` and `land_toi` have no bit.
+    pub fn mask(&self) -> EventMask {
+        let mut m = EventMask::empty();
+        m.set(EventMask::JUMPED, self.jumped != 0);
+        m.set(EventMask::LANDED, self.landed != 0);
+        m.set(EventMask::BONKED, self.bonked != 0);
+        m.set(EventMask::GROUND_REASON, self.ground_reason != 0);
+        m.set(EventMask::FAST_FALLING, self.fast_falling != 0);
+        m.set(EventMask::LEFT_GROUND, self.left_ground != 0);
+        m.set(EventMask::FELL_TOO_FAR, self.fell_too_far != 0);
+        m.set(EventMask::JUMP_WASTED, self.jump_wasted != 0);
+        m.set(EventMask::WALL, self.wall_index >= 0);
+        m
+    }
+}
+
+bitflags::bitflags! {
+    /// Selects `Events` fields (and the matching `SimEvent`s) for
+    /// `Params::disabled_events`. `numeric_fault` is always reported.
+    #[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
+    pub struct EventMask: u16 {
+        const JUMPED        = 1 << 0;
+        const LANDED        = 1 << 1;
+        const BONKED        = 1 << 2;
+        const GROUND_REASON = 1 << 3;
+        const FAST_FALLING  = 1 << 4;
+        const LEFT_GROUND   = 1 << 5;
+        const FELL_TOO_FAR  = 1 << 6;
+        const JUMP_WASTED   = 1 << 7;
+        /// `Events::wall_index`.
+        const WALL          = 1 << 8;
+        /// World-edge bounces (`SimEvent::Bounced`); no `Events` field.
+        const BOUNCED       = 1 << 9;
+    }
+}
+
+/// How the grounded determination at the end of `step` was reached.
+#[repr(u8)]
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum GroundReason {
+    /// Airborne after this frame.
+    None = 0,
+    /// The resolver stopped downward movement on a surface this frame.
+    Contact = 1,
+    /// No direct contact; the ground-snap probe found (and pulled the player onto) a surface.
+    Snap = 2,
+}
+
+/// A typed event with its data, for hosts that prefer a queue over polling
+/// `Events` flags (see [`step_with_events`]).
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub enum SimEvent {
+    /// A jump fired (as `Events::jump_kind`).
+    Jumped { kind: JumpKind },
+    /// Hit a ceiling; `speed` is the upward speed (px/s) it stopped.
+    Bonked { speed: f32 },
+    /// Became grounded; `speed` is the downward speed (px/s) going into collision.
+    Landed { speed: f32 },
+    /// Went from grounded to airborne (walk-off or jump).
+    LeftGround,
+    /// Bounced off a world edge (`world_wrap_mode` 3); `vx` is the reflected velocity.
+    Bounced { vx: f32 },
+    /// Bonked a ceiling with `Params::bonk_restitution` > 0; `vy` is the
+    /// reflected (downward) velocity.
+    BouncedDown { vy: f32 },
+}
+
+/// Which jump branch fired, reported in `Events::jump_kind`.
+#[repr(u8)]
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum JumpKind {
+    /// No jump this frame.
+    None = 0,
+    /// Jumped while grounded at the start of the frame.
+    Ground = 1,
+    /// Jumped from the air inside the coyote window (`coyote_time` /
+    /// `coyote_frames`) or `grounded_grace_frames`.
+    Coyote = 2,
+    /// Reserved for mid-air jumps; this core has none yet.
+    Air = 3,
+    /// Reserved for wall jumps; this core has none yet.
+    Wall = 4,
 }
 
 #[inline]
@@ -133,40 +804,633 @@ fn clamp(x: f32, lo: f32, hi: f32) -> f32 {
     x.max(lo).min(hi)
 }
 
-fn resolve_axis_separated(mut r: Rect, dx: f32, dy: f32, world: &[Rect]) -> (Rect, bool, bool) {
-    let mut hit_ground = false;
-    let mut hit_head = false;
+/// Side of a world rect that a moving rect came into contact with.
+#[repr(u8)]
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum Face {
+    Left,
+    Right,
+    Top,
+    Bottom,
+}
+
+/// Host hook for per-rect surface behavior (bounce, conveyor, damage, sounds)
+/// without baking surface types into the core. See [`step_with_surfaces`].
+pub trait SurfaceBehavior {
+    /// Called when a collision substep stops the player against world rect
+    /// `index` on its `face` (`Face::Top` = landed on it), after the core's own
+    /// velocity response, with `state.x`/`state.y` at the contact position.
+    /// Changes to `state` velocity take effect from the next frame's integration;
+    /// this frame's substep displacement is already fixed. Moving `state.x`/`state.y`
+    /// teleports the player: the frame's remaining substeps are dropped and it
+    /// continues from the new position (teleports don't add to `distance_x`).
+    fn on_contact(&self, state: &mut State, face: Face, index: usize);
+}
+
+/// A single contact produced by the resolver: world index and the face struck.
+type Contact = Option<(usize, Face)>;
+
+/// Push-out target for `r` against `p` along one axis, as (new position, face).
+///
+/// Rects that `r` only entered this move are resolved against the face opposing
+/// the motion (so fast movers can't tunnel). Rects that were already overlapping
+/// before the move are resolved to whichever face is nearer.
+#[inline]
+fn push_out(pos: f32, size: f32, lo: f32, len: f32, delta: f32, was_overlapping: bool, faces: (Face, Face)) -> Option<(f32, Face)> {
+    let before = (lo - size, faces.0);
+    let after = (lo + len, faces.1);
+    if was_overlapping {
+        if (before.0 - pos).abs() <= (after.0 - pos).abs() { Some(before) } else { Some(after) }
+    } else if delta > 0.0 {
+        Some(before)
+    } else if delta < 0.0 {
+        Some(after)
+    } else {
+        None
+    }
+}
+
+/// Pushes `rect` out of every world rect it overlaps, e.g. after a host spawns
+/// or teleports the player into level geometry. `step` runs the same pass on
+/// the hitbox at the start of every frame.
+///
+/// Prefers the nearest position just outside one overlapped rect that is free
+/// of all geometry. Failing that (wedged between several rects), it applies
+/// each overlap's minimum translation vector ([`penetration`]) in turn until
+/// the rect is free. If neither frees it, `rect` is left unchanged.
+pub fn depenetrate(rect: &mut Rect, world: &[Rect]) {
+    *rect = depenetrated(*rect, world, 0.0);
+}
+
+/// Whether `r`'s bottom edge is sunk at most `tolerance` px into the top of `p`:
+/// standing on it after a rounding error rather than pressed into its side.
+#[inline]
+fn resting_in(r: &Rect, p: &Rect, tolerance: f32) -> bool {
+    r.y < p.y && r.y + r.h > p.y && r.y + r.h - p.y <= tolerance
+}
+
+/// By-value [`depenetrate`] over any [`CollisionWorld`]. A rect resting in the
+/// top of a platform (see `resting_in`) is only ever lifted onto it.
+fn depenetrated<W: CollisionWorld + ?Sized>(r: Rect, world: &W, rest_tolerance: f32) -> Rect {
+    let mut best: Option<(f32, Rect)> = None;
+    world.for_each_near(r, |_, p| {
+        if !rects_intersect(&r, &p) {
+            return;
+        }
+        let candidates = [
+            Rect { y: p.y - r.h, ..r },
+            Rect { y: p.y + p.h, ..r },
+            Rect { x: p.x - r.w, ..r },
+            Rect { x: p.x + p.w, ..r },
+        ];
+        let count = if resting_in(&r, &p, rest_tolerance) { 1 } else { candidates.len() };
+        for c in candidates.into_iter().take(count) {
+            let d = (c.x - r.x).abs() + (c.y - r.y).abs();
+            if best.is_some_and(|(bd, _)| d >= bd) {
+                continue;
+            }
+            if !any_intersect(world, c) {
+                best = Some((d, c));
+            }
+        }
+    });
+    if let Some((_, c)) = best {
+        return c;
+    }
+
+    // Iterative MTV fallback, bounded so opposing pushes can't loop forever
+    let mut m = r;
+    for _ in 0..=world.rect_count() {
+        let mut push = None;
+        world.for_each_near(m, |_, p| {
+            if push.is_none() {
+                push = penetration(&m, &p);
+            }
+        });
+        let Some((dx, dy)) = push else { return m };
+        m.x += dx;
+        m.y += dy;
+    }
+    r
+}
+
+/// Axis-separated resolution of one substep. Returns the resolved rect and the
+/// last correcting contact on each axis (X first, then Y).
+///
+/// When several rects overlap on an axis, the smallest correction is applied
+/// first and overlaps are re-checked, so the result does not depend on the
+/// order of `world`. A rect the player is resting in within `rest_tolerance`
+/// (see `resting_in`) never ejects it horizontally; the Y pass lifts it out.
+fn resolve_axis_contacts<W: CollisionWorld + ?Sized>(
+    mut r: Rect,
+    dx: f32,
+    dy: f32,
+    world: &W,
+    rest_tolerance: f32,
+    stats: &mut StepStats,
+) -> (Rect, Contact, Contact) {
+    let mut hit_x = None;
+    let mut hit_y = None;
 
     // X
+    let before = r;
     r.x += dx.round();
-    for p in world {
-        if rects_intersect(&r, p) {
-            if dx > 0.0 { r.x = p.x - r.w; }
-            else if dx < 0.0 { r.x = p.x + p.w; }
+    if dx != 0.0 {
+        for _ in 0..=world.rect_count() {
+            let mut best: Option<(f32, usize, Face)> = None;
+            world.for_each_near(r, |i, p| {
+                stats.intersect_tests += 1;
+                if rects_intersect(&r, &p) && !resting_in(&before, &p, rest_tolerance) {
+                    let was = rects_intersect(&before, &p);
+                    if let Some((x, face)) = push_out(r.x, r.w, p.x, p.w, dx, was, (Face::Left, Face::Right)) {
+                        if best.is_none_or(|(bx, _, _)| (x - r.x).abs() < (bx - r.x).abs()) {
+                            best = Some((x, i, face));
+                        }
+                    }
+                }
+            });
+            let Some((x, i, face)) = best else { break };
+            stats.corrections += 1;
+            r.x = x;
+            hit_x = Some((i, face));
         }
     }
 
     // Y
+    let before = r;
     r.y += dy.round();
-    for p in world {
-        if rects_intersect(&r, p) {
-            if dy > 0.0 {
-                r.y = p.y - r.h;
-                hit_ground = true;
-            } else if dy < 0.0 {
-                r.y = p.y + p.h;
-                hit_head = true;
-            }
+    if dy != 0.0 {
+        for _ in 0..=world.rect_count() {
+            let mut best: Option<(f32, usize, Face)> = None;
+            world.for_each_near(r, |i, p| {
+                stats.intersect_tests += 1;
+                if rects_intersect(&r, &p) {
+                    let was = rects_intersect(&before, &p);
+                    if let Some((y, face)) = push_out(r.y, r.h, p.y, p.h, dy, was, (Face::Top, Face::Bottom)) {
+                        if best.is_none_or(|(by, _, _)| (y - r.y).abs() < (by - r.y).abs()) {
+                            best = Some((y, i, face));
+                        }
+                    }
+                }
+            });
+            let Some((y, i, face)) = best else { break };
+            stats.corrections += 1;
+            r.y = y;
+            hit_y = Some((i, face));
         }
     }
 
+    (r, hit_x, hit_y)
+}
+
+fn resolve_axis_separated<W: CollisionWorld + ?Sized>(
+    r: Rect,
+    dx: f32,
+    dy: f32,
+    world: &W,
+    stats: &mut StepStats,
+) -> (Rect, bool, bool) {
+    let (r, _, hit_y) = resolve_axis_contacts(r, dx, dy, world, 0.0, stats);
+    let hit_ground = matches!(hit_y, Some((_, Face::Top)));
+    let hit_head = matches!(hit_y, Some((_, Face::Bottom)));
     (r, hit_ground, hit_head)
 }
 
+/// Where `rect` would end up after moving by `(dx, dy)` through `world` in one
+/// axis-separated resolve (X then Y, no substeps), plus whether it landed on
+/// or bonked into a surface: `(rect, hit_ground, hit_head)`.
+///
+/// Side-effect-free: the same resolver `step` uses, without touching `State`,
+/// so AI and pathfinding can test a move before committing to it.
+pub fn probe_move(rect: Rect, dx: f32, dy: f32, world: &[Rect]) -> (Rect, bool, bool) {
+    resolve_axis_separated(rect, dx, dy, world, &mut StepStats::default())
+}
+
+/// Result of [`sweep`]: where the rect ended up and what (if anything) stopped it.
+#[derive(Copy, Clone, Debug)]
+pub struct SweepResult {
+    pub rect: Rect,
+    /// Index into `world` of the rect that was struck.
+    pub hit_index: Option<usize>,
+    /// Face of the struck world rect (e.g. `Face::Left` for a rightward shot into a wall).
+    pub face: Option<Face>,
+}
+
+/// General-purpose entity mover using the same substepped, axis-separated
+/// resolution as the player in `step`.
+///
+/// Substeps are bounded by the smaller of the rect's width/height so a moving
+/// entity never skips past a surface thicker than itself. Movement stops at the
+/// first substep that makes contact; the caller decides whether to despawn,
+/// bounce, or slide using `hit_index`/`face`.
+pub fn sweep(rect: Rect, dx: f32, dy: f32, world: &[Rect]) -> SweepResult {
+    let max_step = rect.w.min(rect.h).max(1.0);
+    let steps = (dx.abs().max(dy.abs()) / max_step).ceil().max(1.0) as i32;
+    let sx = dx / (steps as f32);
+    let sy = dy / (steps as f32);
+
+    let mut r = Rect {
+        x: rect.x.round(),
+        y: rect.y.round(),
+        w: rect.w.round(),
+        h: rect.h.round(),
+    };
+
+    for _ in 0..steps {
+        let (r2, hit_x, hit_y) = resolve_axis_contacts(r, sx, sy, world, 0.0, &mut StepStats::default());
+        r = r2;
+        if let Some((i, face)) = hit_x.or(hit_y) {
+            return SweepResult { rect: r, hit_index: Some(i), face: Some(face) };
+        }
+    }
+
+    SweepResult { rect: r, hit_index: None, face: None }
+}
+
 /// One fixed 60Hz step. Host calls this exactly once per frame.
 /// Deterministic at the math/rounding points used here.
-pub fn step(params: &Params, world: &[Rect], s: &mut State, buttons: Buttons) -> Events {
+pub fn step<W: CollisionWorld + ?Sized>(params: &Params, world: &W, s: &mut State, buttons: Buttons) -> Events {
+    step_impl(params, world, s, buttons, &mut StepExtras::default())
+}
+
+/// [`step`] by value: returns the next `State` alongside the frame's `Events`
+/// instead of mutating in place. Handy for property tests and replays that
+/// keep every intermediate state.
+pub fn step_pure<W: CollisionWorld + ?Sized>(
+    params: &Params,
+    world: &W,
+    mut state: State,
+    buttons: Buttons,
+) -> (State, Events) {
+    let ev = step(params, world, &mut state, buttons);
+    (state, ev)
+}
+
+/// Like [`step`], additionally colliding against oriented rects (`obbs`).
+///
+/// `world` keeps the cheap axis-separated AABB path; each OBB the player
+/// overlaps after a substep is resolved by SAT along the minimum translation
+/// vector, removing the velocity component into the surface. Contacts whose
+/// normal points mostly up count as ground, mostly down as a head bonk.
+pub fn step_with_obbs<W: CollisionWorld + ?Sized>(
+    params: &Params,
+    world: &W,
+    obbs: &[ObbRect],
+    s: &mut State,
+    buttons: Buttons,
+) -> Events {
+    step_impl(params, world, s, buttons, &mut StepExtras { obbs, ..StepExtras::default() })
+}
+
+/// Like [`step`], but advances simulated time by `DT * time_scale` for this
+/// frame (e.g. `0.5` = half speed for hit-stop / bullet-time).
+///
+/// Accelerations, timer decay, and displacement all use the scaled delta;
+/// collision resolution and rounding are unchanged. The host still calls this
+/// once per 60Hz tick. A run is only reproducible if the per-frame
+/// `time_scale` sequence is recorded alongside the inputs.
+pub fn step_scaled<W: CollisionWorld + ?Sized>(
+    params: &Params,
+    world: &W,
+    s: &mut State,
+    buttons: Buttons,
+    time_scale: f32,
+) -> Events {
+    step_impl(params, world, s, buttons, &mut StepExtras { time_scale, ..StepExtras::default() })
+}
+
+/// Like [`step`], calling `on_substep` with the player rect after each
+/// collision substep of the integration loop (including OBB push-out).
+///
+/// Intended for debug visualisation of tunnelling and corner snags. The
+/// callback only observes; the resulting state and events are identical to
+/// [`step`]. Ground-snap and world wrap run after the last substep and are not
+/// reported.
+pub fn step_with_substeps<W: CollisionWorld + ?Sized>(
+    params: &Params,
+    world: &W,
+    s: &mut State,
+    buttons: Buttons,
+    on_substep: &mut dyn FnMut(Rect),
+) -> Events {
+    let mut extras = StepExtras { on_substep: Some(on_substep), ..StepExtras::default() };
+    step_impl(params, world, s, buttons, &mut extras)
+}
+
+/// Like [`step`], taking the extended [`Input`] (analog horizontal axis).
+///
+/// `move_x` goes through `Params::input_deadzone` and then
+/// `Params::input_smoothing` before it drives movement; a stick resting in the
+/// deadzone falls back to the button bits like `move_x == 0`.
+pub fn step_input<W: CollisionWorld + ?Sized>(params: &Params, world: &W, s: &mut State, input: &Input) -> Events {
+    let buttons = Buttons::from_bits_truncate(input.bits);
+    let raw = if input.move_x.is_finite() { clamp(input.move_x, -1.0, 1.0) } else { 0.0 };
+    let move_x = shape_move_x(params, s, raw);
+    step_impl(params, world, s, buttons, &mut StepExtras { move_x, ..StepExtras::default() })
+}
+
+/// Below this the smoothed axis snaps to 0 so a released stick settles.
+const SMOOTHED_MOVE_EPSILON: f32 = 1e-3;
+
+/// Deadzone + rescale, then exponential smoothing (stored in `s.move_x_smoothed`).
+fn shape_move_x(params: &Params, s: &mut State, raw: f32) -> f32 {
+    let dz = clamp(params.input_deadzone, 0.0, 0.99);
+    let mut m = if raw.abs() <= dz { 0.0 } else { sign(raw) * (raw.abs() - dz) / (1.0 - dz) };
+
+    let keep = clamp(params.input_smoothing, 0.0, 0.99);
+    if keep > 0.0 {
+        m = s.move_x_smoothed * keep + m * (1.0 - keep);
+        if m.abs() < SMOOTHED_MOVE_EPSILON {
+            m = 0.0;
+        }
+    }
+    s.move_x_smoothed = m;
+    m
+}
+
+/// Like [`step`], adding `(ext_vx, ext_vy)` px/s to the player's velocity just
+/// before integration (after movement, gravity, jump and the speed clamps), so
+/// the shove goes through collision and can't push through walls.
+///
+/// Nothing is stored: pass the value again next frame to keep pushing. The
+/// added velocity does carry into later frames like any other velocity, until
+/// the usual clamps, friction and gravity act on it.
+pub fn step_ext<W: CollisionWorld + ?Sized>(
+    params: &Params,
+    world: &W,
+    s: &mut State,
+    buttons: Buttons,
+    ext_vx: f32,
+    ext_vy: f32,
+) -> Events {
+    step_impl(params, world, s, buttons, &mut StepExtras { ext_v: (ext_vx, ext_vy), ..StepExtras::default() })
+}
+
+/// Collision work done by [`step_profiled`], accumulated across calls.
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq)]
+pub struct StepStats {
+    /// Candidate rects overlap-tested by the substep resolver.
+    pub intersect_tests: u32,
+    /// Collision substeps run.
+    pub substeps: u32,
+    /// Push-outs applied by the resolver (AABB and OBB).
+    pub corrections: u32,
+}
+
+/// Like [`step`], adding this frame's collision counters to `stats`.
+///
+/// Counts cover the substep integration loop only (not depenetration or the
+/// ground-snap probe). For a brute-force `&[Rect]` world with no contacts and
+/// movement on both axes, `intersect_tests == 2 * world.len() * substeps`.
+pub fn step_profiled<W: CollisionWorld + ?Sized>(
+    params: &Params,
+    world: &W,
+    s: &mut State,
+    buttons: Buttons,
+    stats: &mut StepStats,
+) -> Events {
+    step_impl(params, world, s, buttons, &mut StepExtras { stats: Some(stats), ..StepExtras::default() })
+}
+
+/// Like [`step`], calling `surfaces.on_contact` for each world rect the
+/// collision substeps stop the player against (at most one per axis per substep).
+pub fn step_with_surfaces<W: CollisionWorld + ?Sized>(
+    params: &Params,
+    world: &W,
+    s: &mut State,
+    buttons: Buttons,
+    surfaces: &dyn SurfaceBehavior,
+) -> Events {
+    step_impl(params, world, s, buttons, &mut StepExtras { surfaces: Some(surfaces), ..StepExtras::default() })
+}
+
+/// Like [`step`], also appending the frame's events to `out` as [`SimEvent`]s in
+/// the order the frame resolved them: the jump, collision contacts, grounding
+/// changes, then a world-edge bounce. `out` is not cleared, and
+/// `Params::disabled_events` silences the matching entries.
+pub fn step_with_events<W: CollisionWorld + ?Sized>(
+    params: &Params,
+    world: &W,
+    s: &mut State,
+    buttons: Buttons,
+    out: &mut Vec<SimEvent>,
+) -> Events {
+    step_impl(params, world, s, buttons, &mut StepExtras { sim_events: Some(out), ..StepExtras::default() })
+}
+
+/// Optional inputs to `step_impl` used by the `step_*` variants.
+struct StepExtras<'a> {
+    time_scale: f32,
+    obbs: &'a [ObbRect],
+    on_substep: Option<&'a mut dyn FnMut(Rect)>,
+    /// Analog horizontal axis in [-1, 1]; 0 = use LEFT/RIGHT.
+    move_x: f32,
+    stats: Option<&'a mut StepStats>,
+    /// Host-supplied velocity added before integration (`step_ext`).
+    ext_v: (f32, f32),
+    surfaces: Option<&'a dyn SurfaceBehavior>,
+    sim_events: Option<&'a mut Vec<SimEvent>>,
+}
+
+impl StepExtras<'_> {
+    fn emit(&mut self, event: SimEvent) {
+        if let Some(queue) = self.sim_events.as_mut() {
+            queue.push(event);
+        }
+    }
+}
+
+impl Default for StepExtras<'_> {
+    fn default() -> Self {
+        Self {
+            time_scale: 1.0,
+            obbs: &[],
+            on_substep: None,
+            move_x: 0.0,
+            stats: None,
+            ext_v: (0.0, 0.0),
+            surfaces: None,
+            sim_events: None,
+        }
+    }
+}
+
+/// The part of hitbox `r` that grounding looks under: `Params::foot_width_fraction`
+/// of its width, centred and pixel-aligned (all of `r` at 1.0).
+fn foot_probe(params: &Params, r: Rect) -> Rect {
+    let fraction = params.foot_width_fraction.clamp(0.0, 1.0);
+    if fraction >= 1.0 {
+        return r;
+    }
+    let w = (r.w * fraction).round().max(1.0);
+    Rect { x: r.x + ((r.w - w) / 2.0).round(), w, ..r }
+}
+
+/// The smallest ledge (1px tall, top at `y`) that grounds a hitbox `w` wide
+/// standing with its left edge at `x`: the foot span `step`'s ground check
+/// probes. A platform there counts as ground only if it reaches into this span;
+/// one covering just the hitbox corners outside it does not.
+pub fn min_ground_rect(params: &Params, x: f32, w: f32, y: f32) -> Rect {
+    let foot = foot_probe(params, Rect { x: x.round(), y, w: w.round(), h: 1.0 });
+    Rect { y, h: 1.0, ..foot }
+}
+
+/// Rounds away from zero so an MTV push always fully separates on the pixel grid.
+#[inline]
+fn round_out(v: f32) -> f32 {
+    sign(v) * v.abs().ceil()
+}
+
+/// `world_wrap_mode` 1: the edge wrap from `reference/pygame_sandbox/tuner.py`,
+/// reproduced bit-for-bit. Returns the new left edge for a rect at `x` that is
+/// `w` wide.
+///
+/// Matches the pygame integer `Rect` step exactly: `x`, `w` and `world_w` are
+/// rounded half-to-even (Python's `round`), and a single boundary check runs once
+/// per frame: left of 0 snaps flush to the right edge, past the right edge
+/// snaps to 0. There is no minimum world width, so a world narrower than the
+/// rect keeps re-snapping, as in pygame. `step` applies it even when the player
+/// lands inside a solid rect (modes 2 and 3 refuse such wraps), again as in pygame.
+pub fn wrap_pygame_legacy(x: f32, w: f32, world_w: f32) -> f32 {
+    let world_w = world_w.round_ties_even();
+    let w = w.round_ties_even();
+    let left = x.round_ties_even();
+    if left < 0.0 {
+        world_w - w
+    } else if left + w > world_w {
+        0.0
+    } else {
+        left
+    }
+}
+
+/// Optional world wrap (torus), based on center.
+fn apply_world_wrap(params: &Params, s: &mut State) {
+    let wrap_mode = params.world_wrap_mode.round() as i32;
+    if wrap_mode == 1 {
+        s.x = wrap_pygame_legacy(s.x, s.w, params.world_w);
+    } else if wrap_mode == 2 {
+        let w = params.world_w.max(MIN_WORLD_W);
+        let center_x = s.x + 0.5 * s.w;
+        let wrapped = ((center_x % w) + w) % w;
+        s.x = (wrapped - 0.5 * s.w).round();
+    } else if wrap_mode == 3 {
+        let w = params.world_w.max(MIN_WORLD_W).round();
+        let width = s.w.round();
+        if s.x < 0.0 {
+            s.x = 0.0;
+            if s.vx < 0.0 { s.vx = -s.vx * params.world_bounce_restitution; }
+        } else if s.x + width > w {
+            s.x = w - width;
+            if s.vx > 0.0 { s.vx = -s.vx * params.world_bounce_restitution; }
+        }
+    }
+}
+
+/// The world rectangle `{0, 0, world_w, world_h}`. With `world_h == 0` it
+/// spans [`UNBOUNDED_WORLD_H`] centred on `y = 0`, so anything above or below
+/// the playfield still counts as inside.
+pub fn world_bounds(params: &Params) -> Rect {
+    if params.world_h > 0.0 {
+        Rect { x: 0.0, y: 0.0, w: params.world_w, h: params.world_h }
+    } else {
+        Rect { x: 0.0, y: -UNBOUNDED_WORLD_H / 2.0, w: params.world_w, h: UNBOUNDED_WORLD_H }
+    }
+}
+
+/// Whether `rect` lies entirely inside [`world_bounds`] (touching an edge counts).
+pub fn in_world_bounds(params: &Params, rect: &Rect) -> bool {
+    let b = world_bounds(params);
+    rect.x >= b.x && rect.y >= b.y && rect.x + rect.w <= b.x + b.w && rect.y + rect.h <= b.y + b.h
+}
+
+/// Fills `out` (cleared first) with the indices, ascending, of `world` rects
+/// overlapping `view`: the renderer's culling query. Sub-linear when `world`
+/// has a broadphase behind `for_each_near`.
+pub fn rects_in_view<W: CollisionWorld + ?Sized>(view: &Rect, world: &W, out: &mut Vec<usize>) {
+    out.clear();
+    world.for_each_near(*view, |i, p| {
+        if rects_intersect(view, &p) {
+            out.push(i);
+        }
+    });
+}
+
+/// [`rects_in_view`] for a wrapping world (`world_wrap_mode` 1 or 2): a view
+/// crossing the `0`/`world_w` seam also picks up rects on the far side.
+pub fn rects_in_view_wrapped<W: CollisionWorld + ?Sized>(
+    params: &Params,
+    view: &Rect,
+    world: &W,
+    out: &mut Vec<usize>,
+) {
+    rects_in_view(view, world, out);
+    let wrap_mode = params.world_wrap_mode.round() as i32;
+    if wrap_mode != 1 && wrap_mode != 2 {
+        return;
+    }
+    let w = params.world_w.max(MIN_WORLD_W);
+    for shifted in [Rect { x: view.x - w, ..*view }, Rect { x: view.x + w, ..*view }] {
+        world.for_each_near(shifted, |i, p| {
+            if rects_intersect(&shifted, &p) {
+                out.push(i);
+            }
+        });
+    }
+    out.sort_unstable();
+    out.dedup();
+}
+
+/// Events `step` should fill in, per `Params::disabled_events`.
+fn reported_events(params: &Params) -> EventMask {
+    !EventMask::from_bits_truncate(params.disabled_events.clamp(0.0, u16::MAX as f32) as u16)
+}
+
+/// Free flight for `Params::noclip`: velocity comes straight from the inputs on both
+/// axes and the rect moves without gravity, collision or snap. Timers are cleared so
+/// leaving noclip mid-air behaves like a plain fall. An analog `move_x` (from
+/// `step_input`) scales horizontal speed as it does outside noclip.
+fn step_noclip(params: &Params, s: &mut State, buttons: Buttons, move_dir: i32, move_x: f32, dt: f32) -> Events {
+    let run_mul = if buttons.contains(Buttons::RUN) { params.run_multiplier } else { 1.0 };
+    let speed = params.ground_max_speed * run_mul;
+    let speed_x = if move_x != 0.0 { speed * move_x.abs() } else { speed };
+    let dir_y = buttons.contains(Buttons::DOWN) as i32 - buttons.contains(Buttons::JUMP) as i32;
+
+    s.vx = move_dir as f32 * speed_x;
+    s.vy = dir_y as f32 * speed;
+    s.x += s.vx * dt;
+    s.y += s.vy * dt;
+
+    let was_grounded = s.grounded != 0;
+    s.grounded = 0;
+    s.coyote = 0.0;
+    s.grounded_recently = 0;
+    s.jump_cut_timer = 0;
+    s.jump_buffer = 0.0;
+    s.jump_was_down = buttons.contains(Buttons::JUMP) as u8;
+    s.snap_suppress = 0;
+    s.just_landed = 0;
+    s.pushing = 0;
+    s.ground_normal_x = 0.0;
+    s.ground_normal_y = 0.0;
+
+    apply_world_wrap(params, s);
+    let report = reported_events(params);
+    let left_ground = was_grounded && report.contains(EventMask::LEFT_GROUND);
+    Events { left_ground: left_ground as u8, ..Events::default() }
+}
+
+fn step_impl<W: CollisionWorld + ?Sized>(
+    params: &Params,
+    world: &W,
+    s: &mut State,
+    buttons: Buttons,
+    extras: &mut StepExtras,
+) -> Events {
     let mut ev = Events::default();
+    let report = reported_events(params);
+    let dt = DT * extras.time_scale.max(0.0);
+    let (start_x, start_y) = (s.x, s.y);
 
     let left = buttons.contains(Buttons::LEFT);
     let right = buttons.contains(Buttons::RIGHT);
@@ -174,7 +1438,15 @@ pub fn step(params: &Params, world: &[Rect], s: &mut State, buttons: Buttons) ->
     let run = buttons.contains(Buttons::RUN);
     let jump = buttons.contains(Buttons::JUMP);
 
-    let move_dir = (right as i32) - (left as i32);
+    let move_dir = if extras.move_x != 0.0 {
+        sign(extras.move_x) as i32
+    } else {
+        (right as i32) - (left as i32)
+    };
+
+    if params.noclip > 0.0 {
+        return step_noclip(params, s, buttons, move_dir, extras.move_x, dt);
+    }
 
     // Jump edge detection
     let jump_was_down = s.jump_was_down != 0;
@@ -184,22 +1456,43 @@ pub fn step(params: &Params, world: &[Rect], s: &mut State, buttons: Buttons) ->
 
     let was_grounded = s.grounded != 0;
 
-    // Coyote timer
+    // Coyote timer (only re-armed after a full grounded frame). Frame-count
+    // timers tick after the jump check instead, so N frames means N checks.
+    let coyote_in_frames = params.coyote_frames > 0.0;
     if was_grounded {
-        s.coyote = params.coyote_time;
-    } else {
-        s.coyote = (s.coyote - DT).max(0.0);
+        if s.just_landed == 0 {
+            s.coyote = if coyote_in_frames { params.coyote_frames.round() } else { params.coyote_time };
+        }
+        s.grounded_recently = params.grounded_grace_frames.round().clamp(0.0, 255.0) as u8;
+    } else if !coyote_in_frames {
+        s.coyote = (s.coyote - dt).max(0.0);
     }
 
     // Jump buffer timer
+    let buffer_in_frames = params.jump_buffer_frames > 0.0;
+    let buffer_was_live = s.jump_buffer > 0.0;
     if jump_pressed {
-        s.jump_buffer = params.jump_buffer;
+        s.jump_buffer = if buffer_in_frames { params.jump_buffer_frames.round() } else { params.jump_buffer };
+    } else if !buffer_in_frames {
+        s.jump_buffer = (s.jump_buffer - dt).max(0.0);
+    }
+    let mut buffer_expired = buffer_was_live && s.jump_buffer == 0.0;
+
+    // Run ramp
+    let run_target = if run { 1.0 } else { 0.0 };
+    if params.run_ramp_time > 0.0 {
+        let rate = dt / params.run_ramp_time;
+        s.run_ramp = clamp(s.run_ramp + clamp(run_target - s.run_ramp, -rate, rate), 0.0, 1.0);
     } else {
-        s.jump_buffer = (s.jump_buffer - DT).max(0.0);
+        s.run_ramp = run_target;
     }
 
     // Horizontal movement
-    let run_mul = if run { params.run_multiplier } else { 1.0 };
+    let run_mul = if s.run_ramp >= 1.0 {
+        params.run_multiplier
+    } else {
+        1.0 + (params.run_multiplier - 1.0) * s.run_ramp
+    };
     let (max_speed, accel, decel, friction) = if was_grounded {
         (
             params.ground_max_speed * run_mul,
@@ -208,9 +1501,20 @@ pub fn step(params: &Params, world: &[Rect], s: &mut State, buttons: Buttons) ->
             params.ground_friction,
         )
     } else {
+        let falling = s.vy > 0.0;
+        let air_max_speed = if falling && params.air_max_speed_falling >= 0.0 {
+            params.air_max_speed_falling
+        } else {
+            params.air_max_speed
+        };
+        let air_accel = if falling && params.air_accel_falling >= 0.0 {
+            params.air_accel_falling
+        } else {
+            params.air_accel
+        };
         (
-            params.air_max_speed * run_mul,
-            params.air_accel,
+            air_max_speed * run_mul,
+            air_accel,
             params.air_decel,
             0.0,
         )
@@ -219,134 +1523,582 @@ pub fn step(params: &Params, world: &[Rect], s: &mut State, buttons: Buttons) ->
     if move_dir != 0 {
         let desired_dir = move_dir as f32;
         let turning = s.vx != 0.0 && sign(s.vx) != desired_dir;
-        let dv = if turning { decel } else { accel } * DT * desired_dir;
+        let dv = if turning { decel } else { accel } * dt * desired_dir;
         s.vx += dv;
     } else if was_grounded {
-        let fr = friction * DT;
+        let fr = friction * dt;
         if s.vx.abs() <= fr { s.vx = 0.0; }
         else { s.vx -= sign(s.vx) * fr; }
     }
 
     // Air drag
     if !was_grounded && params.air_drag > 0.0 {
-        let drag = params.air_drag * DT;
+        let drag = params.air_drag * dt;
         if s.vx.abs() <= drag { s.vx = 0.0; }
         else { s.vx -= sign(s.vx) * drag; }
     }
 
+    let max_speed = if extras.move_x != 0.0 { max_speed * extras.move_x.abs() } else { max_speed };
     s.vx = clamp(s.vx, -max_speed, max_speed);
 
     // Gravity
-    let g = if s.vy < 0.0 { params.gravity_up } else { params.gravity_down };
+    let g_split = if s.vy < 0.0 { params.gravity_up } else { params.gravity_down };
+    let g = if params.gravity_blend > 0.0 {
+        let span = (params.jump_velocity + params.terminal_velocity).max(1.0);
+        let t = clamp((s.vy + params.jump_velocity) / span, 0.0, 1.0);
+        let g_curve = params.gravity_up + (params.gravity_down - params.gravity_up) * t;
+        g_split + (g_curve - g_split) * params.gravity_blend.min(1.0)
+    } else {
+        g_split
+    };
     let mut g_apply = g;
-    if down && s.vy > 0.0 {
+    let auto_fast_fall = params.auto_fast_fall_after > 0.0 && s.airtime_frames as f32 > params.auto_fast_fall_after;
+    if (down || auto_fast_fall) && s.vy > 0.0 {
         g_apply *= params.fast_fall_multiplier;
+        if report.contains(EventMask::FAST_FALLING) { ev.fast_falling = 1; }
     }
-    s.vy += g_apply * DT;
-    s.vy = clamp(s.vy, -5000.0, params.terminal_velocity);
+    if params.wall_proximity_gravity_scale != 1.0 && !was_grounded && s.vy > 0.0 {
+        let hb = s.hitbox();
+        let near_left = Rect { x: hb.x - WALL_PROXIMITY_PX, w: WALL_PROXIMITY_PX, ..hb };
+        let near_right = Rect { x: hb.x + hb.w, w: WALL_PROXIMITY_PX, ..hb };
+        if any_intersect(world, near_left) || any_intersect(world, near_right) {
+            g_apply *= params.wall_proximity_gravity_scale;
+        }
+    }
+    if s.jump_cut_timer > 0 {
+        g_apply *= params.jump_cut_gravity_boost;
+        s.jump_cut_timer -= 1;
+    }
+    let vy_before_gravity = s.vy;
+    s.vy += g_apply * dt;
+    s.vy = clamp(s.vy, -params.max_rise_speed, params.terminal_velocity);
+    // What this frame's gravity added; explicit Euler moves without it
+    let mut gravity_dv = s.vy - vy_before_gravity;
 
     // Jump execution
-    let can_jump = was_grounded || s.coyote > 0.0;
-    let wants_jump = s.jump_buffer > 0.0;
+    let mut jumped = false;
+    let can_jump = was_grounded || s.coyote > 0.0 || s.grounded_recently > 0;
+    let auto_bounce = params.auto_bounce > 0.0 && jump && was_grounded && s.just_landed != 0;
+    let wants_jump = s.jump_buffer > 0.0 || auto_bounce;
     if can_jump && wants_jump {
+        s.jump_launch_y = s.y;
+        s.jump_active = 1;
+        s.last_jump_apex_height = 0.0;
         s.vy = -params.jump_velocity;
         s.grounded = 0;
         s.coyote = 0.0;
+        s.grounded_recently = 0;
+        s.jump_cut_timer = 0;
         s.jump_buffer = 0.0;
-        ev.jumped = 1;
+        gravity_dv = 0.0;
+        jumped = true;
+        if report.contains(EventMask::JUMPED) {
+            ev.jumped = 1;
+            let kind = if was_grounded { JumpKind::Ground } else { JumpKind::Coyote };
+            ev.jump_kind = kind as u8;
+            extras.emit(SimEvent::Jumped { kind });
+        }
+    }
+    if coyote_in_frames && !was_grounded {
+        s.coyote = (s.coyote - 1.0).max(0.0);
+    }
+    if !was_grounded {
+        s.grounded_recently = s.grounded_recently.saturating_sub(1);
+    }
+    if buffer_in_frames && s.jump_buffer > 0.0 {
+        s.jump_buffer = (s.jump_buffer - 1.0).max(0.0);
+        buffer_expired = s.jump_buffer == 0.0;
+    }
+    if buffer_expired && !was_grounded && report.contains(EventMask::JUMP_WASTED) {
+        ev.jump_wasted = 1;
     }
 
     // Jump cut
     if jump_released && s.vy < 0.0 {
-        let cut_vy = -params.jump_velocity * params.jump_cut_multiplier;
-        if s.vy < cut_vy { s.vy = cut_vy; }
+        let cut_vy = -(params.jump_velocity * params.jump_cut_multiplier).max(params.min_jump_velocity);
+        if s.vy < cut_vy {
+            s.vy = cut_vy;
+            gravity_dv = 0.0;
+            s.jump_cut_timer = params.jump_cut_boost_frames.round().clamp(0.0, 255.0) as u8;
+        }
     }
 
-    // Integrate with substeps + collisions
-    let mut rect = Rect {
-        x: s.x.round(),
-        y: s.y.round(),
-        w: s.w.round(),
-        h: s.h.round(),
-    };
-
-    let max_step = params.max_step_px.max(1.0);
-    let total_dx = s.vx * DT;
-    let total_dy = s.vy * DT;
+    // Host shove (step_ext)
+    if extras.ext_v != (0.0, 0.0) {
+        s.vx += extras.ext_v.0;
+        s.vy += extras.ext_v.1;
+    }
 
-    let steps = ((total_dx.abs().max(total_dy.abs())) / max_step).ceil().max(1.0) as i32;
-    let dx = total_dx / (steps as f32);
-    let dy = total_dy / (steps as f32);
+    // Non-finite velocity (bad params) would poison position and the substep count
+    if !s.vx.is_finite() || !s.vy.is_finite() {
+        s.vx = 0.0;
+        s.vy = 0.0;
+        gravity_dv = 0.0;
+        ev.numeric_fault = 1;
+    }
+
+    // Scripted axis locks
+    let freeze = params.freeze_axes.clamp(0.0, 3.0) as u8;
+    let (freeze_x, freeze_y) = (freeze & 1 != 0, freeze & 2 != 0);
+    if freeze_x {
+        s.vx = 0.0;
+    }
+    if freeze_y {
+        s.vy = 0.0;
+        gravity_dv = 0.0;
+    }
+
+    // Velocity used for this frame's displacement
+    let mut vy_move = if params.integration_mode.round() as i32 == 1 { s.vy - gravity_dv } else { s.vy };
+
+    // Integrate with substeps + collisions (on the hitbox)
+    let hitbox = s.hitbox();
+    let mut rect = Rect {
+        x: hitbox.x.round(),
+        y: hitbox.y.round(),
+        w: hitbox.w.round(),
+        h: hitbox.h.round(),
+    };
+
+    // Runtime resizing can leave the rect embedded; free it before moving. Feet
+    // sunk up to the ground-snap distance into a platform are lifted onto it,
+    // never shoved sideways off it
+    let rest_tolerance = params.snap_to_ground.round().max(0.0);
+    rect = depenetrated(rect, world, rest_tolerance);
+    let rect_start_x = rect.x;
+
+    let min_step = params.min_max_step.max(MIN_STEP_PX_FLOOR);
+    let min_substeps = params.min_substeps.max(1.0).floor();
+    let axis_step = |v: f32| if v > 0.0 { v } else { params.max_step_px };
+    let max_step = axis_step(params.max_step_px_x).max(min_step);
+    // Vertical travel measured in horizontal steps (exactly 1 without overrides)
+    let y_scale = max_step / axis_step(params.max_step_px_y).max(min_step);
+
+    // Bound worst-case cost: a frame that would need more than `max_substeps`
+    // substeps has its velocity scaled down (keeping direction) to fit the cap
+    let max_travel = params.max_substeps.max(min_substeps).floor() * max_step;
+    let travel = (s.vx * dt).abs().max((vy_move * dt).abs() * y_scale);
+    if travel > max_travel {
+        let k = max_travel / travel;
+        s.vx *= k;
+        s.vy *= k;
+        vy_move *= k;
+    }
+
+    let total_dx = s.vx * dt;
+    let total_dy = vy_move * dt;
+
+    let steps = ((total_dx.abs().max(total_dy.abs() * y_scale)) / max_step).ceil().max(min_substeps) as i32;
+    let dx = total_dx / (steps as f32);
+    let dy = total_dy / (steps as f32);
 
     let mut hit_ground_any = false;
+    let mut aabb_ground = false;
+    let mut obb_normal: Option<(f32, f32)> = None;
+    let mut stats = StepStats { substeps: steps as u32, ..StepStats::default() };
+    let mut ground_substep: Option<i32> = None;
+    let mut teleport_dx = 0.0;
+    (ev.vx_pre_collision, ev.vy_pre_collision) = (s.vx, s.vy);
 
-    for _ in 0..steps {
-        let (r2, hit_ground, hit_head) = resolve_axis_separated(rect, dx, dy, world);
+    for i in 0..steps {
+        let (r2, hit_x, hit_y) = resolve_axis_contacts(rect, dx, dy, world, rest_tolerance, &mut stats);
         rect = r2;
+        let hit_ground = matches!(hit_y, Some((_, Face::Top)));
+        let hit_head = matches!(hit_y, Some((_, Face::Bottom)));
 
-        if hit_head && s.vy < 0.0 { s.vy = 0.0; ev.bonked = 1; }
+        if let Some((index, _)) = hit_x.filter(|_| report.contains(EventMask::WALL)) {
+            ev.wall_index = index as i32;
+        }
+        if hit_head && s.vy < 0.0 {
+            if report.contains(EventMask::BONKED) {
+                ev.bonked = 1;
+                ev.bonk_index = hit_y.map_or(-1, |(index, _)| index as i32);
+                extras.emit(SimEvent::Bonked { speed: -s.vy });
+            }
+            s.vy = -s.vy * params.bonk_restitution.max(0.0);
+            if s.vy > 0.0 && report.contains(EventMask::BONKED) {
+                extras.emit(SimEvent::BouncedDown { vy: s.vy });
+            }
+        }
         if hit_ground && s.vy > 0.0 { s.vy = 0.0; }
+        let mut teleported = false;
+        if let Some(surfaces) = extras.surfaces {
+            let contact = (rect.x - s.hitbox_offset_x, rect.y - s.hitbox_offset_y);
+            (s.x, s.y) = contact;
+            for (index, face) in [hit_x, hit_y].into_iter().flatten() {
+                surfaces.on_contact(s, face, index);
+            }
+            if (s.x, s.y) != contact {
+                let hitbox = s.hitbox();
+                (rect.x, rect.y) = (hitbox.x.round(), hitbox.y.round());
+                teleport_dx += rect.x - s.hitbox_offset_x - contact.0;
+                teleported = true;
+            }
+        }
+        aabb_ground |= hit_ground;
 
         hit_ground_any |= hit_ground;
+
+        for obb in extras.obbs {
+            if let Some((mx, my)) = aabb_obb_mtv(&rect, obb) {
+                stats.corrections += 1;
+                rect.x += round_out(mx);
+                rect.y += round_out(my);
+
+                let len = (mx * mx + my * my).sqrt();
+                let (nx, ny) = (mx / len, my / len);
+                let vn = s.vx * nx + s.vy * ny;
+                if vn < 0.0 {
+                    s.vx -= vn * nx;
+                    s.vy -= vn * ny;
+                }
+                if ny < -0.5 {
+                    hit_ground_any = true;
+                    obb_normal = Some((nx, ny));
+                } else if ny > 0.5 && vn < 0.0 && report.contains(EventMask::BONKED) {
+                    ev.bonked = 1;
+                    extras.emit(SimEvent::Bonked { speed: -vn });
+                }
+            }
+        }
+
+        if hit_ground_any && ground_substep.is_none() {
+            ground_substep = Some(i);
+        }
+
+        if let Some(cb) = extras.on_substep.as_mut() {
+            cb(rect);
+        }
+        if teleported {
+            break;
+        }
+    }
+
+    // Narrow foot: a resolver landing with nothing under the foot isn't ground
+    let narrow_foot = params.foot_width_fraction < 1.0;
+    let foot = |r: Rect| foot_probe(params, r);
+    let under_foot = Rect { y: rect.y + 1.0, ..foot(rect) };
+    if narrow_foot && aabb_ground && obb_normal.is_none() && !any_intersect(world, under_foot) {
+        hit_ground_any = false;
+        aabb_ground = false;
+        ground_substep = None;
+    }
+
+    if let Some(out) = extras.stats.as_mut() {
+        out.substeps += stats.substeps;
+        out.intersect_tests += stats.intersect_tests;
+        out.corrections += stats.corrections;
+    }
+
+    s.x = rect.x - s.hitbox_offset_x;
+    s.y = rect.y - s.hitbox_offset_y;
+    if freeze_x {
+        s.x = start_x;
     }
+    if freeze_y {
+        s.y = start_y;
+    }
+    if start_x.is_finite() && s.x.is_finite() {
+        s.distance_x += (s.x - start_x - teleport_dx).abs();
+    }
+
+    // World wrap, before the grounding checks so they see the side the player
+    // ends up on. In modes 2 and 3 a wrap that would embed the player in a solid
+    // rect is refused; mode 1 always wraps, as pygame does.
+    let (pre_wrap_x, pre_wrap_vx) = (s.x, s.vx);
+    apply_world_wrap(params, s);
+    let pygame_wrap = params.world_wrap_mode.round() as i32 == 1;
+    if s.x != pre_wrap_x && !pygame_wrap && any_intersect(world, s.hitbox()) {
+        (s.x, s.vx) = (pre_wrap_x, pre_wrap_vx);
+    }
+    rect.x += s.x - pre_wrap_x;
+    let bounced = s.vx != pre_wrap_vx && params.world_wrap_mode.round() as i32 == 3;
 
-    s.x = rect.x;
-    s.y = rect.y;
+    // Ground snap (skipped on the jump frame and for a few frames after, so the
+    // probe can't pull a slow takeoff back onto the ground)
+    let snap_suppressed = if jumped {
+        s.snap_suppress = params.jump_snap_suppress_frames.max(0.0).round().min(255.0) as u8;
+        true
+    } else if s.snap_suppress > 0 {
+        s.snap_suppress -= 1;
+        true
+    } else {
+        false
+    };
 
-    // Ground snap
     let mut now_grounded = false;
-    if params.snap_to_ground > 0.0 {
-        let test = Rect {
-            x: rect.x,
-            y: rect.y + params.snap_to_ground.round(),
-            w: rect.w,
-            h: rect.h,
-        };
-        for p in world {
-            if rects_intersect(&test, p) {
+    if params.snap_to_ground > 0.0 && !snap_suppressed {
+        let test = Rect { y: rect.y + params.snap_to_ground.round(), ..foot(rect) };
+        // First (lowest-index) surface under the probe wins
+        let mut surface: Option<Rect> = None;
+        world.for_each_near(test, |_, p| {
+            if surface.is_none() && rects_intersect(&test, &p) {
+                surface = Some(p);
+            }
+        });
+        if let Some(p) = surface {
+            now_grounded = true;
+            aabb_ground = true;
+            if !freeze_y && rect.y + rect.h <= p.y + params.snap_to_ground.round() {
+                rect.y = p.y - rect.h;
+                s.y = rect.y - s.hitbox_offset_y;
+            }
+        }
+        if !now_grounded {
+            if let Some((mx, my)) = extras.obbs.iter().find_map(|obb| aabb_obb_mtv(&test, obb)) {
                 now_grounded = true;
-                if rect.y + rect.h <= p.y + params.snap_to_ground.round() {
-                    rect.y = p.y - rect.h;
-                    s.y = rect.y;
+                let len = (mx * mx + my * my).sqrt();
+                if my < 0.0 {
+                    obb_normal = Some((mx / len, my / len));
                 }
-                break;
             }
         }
     } else {
         now_grounded = hit_ground_any;
     }
 
-    if now_grounded && !was_grounded {
+    if now_grounded && report.contains(EventMask::GROUND_REASON) {
+        ev.ground_reason = if hit_ground_any { GroundReason::Contact } else { GroundReason::Snap } as u8;
+    }
+
+    if now_grounded && !was_grounded && report.contains(EventMask::LANDED) {
         ev.landed = 1;
+        ev.land_toi = ground_substep.map_or(1.0, |i| (i + 1) as f32 / steps as f32);
+        extras.emit(SimEvent::Landed { speed: ev.vy_pre_collision.max(0.0) });
+    }
+    if was_grounded && !now_grounded && report.contains(EventMask::LEFT_GROUND) {
+        ev.left_ground = 1;
+        extras.emit(SimEvent::LeftGround);
+    }
+    if bounced && report.contains(EventMask::BOUNCED) {
+        extras.emit(SimEvent::Bounced { vx: s.vx });
     }
 
     s.grounded = if now_grounded { 1 } else { 0 };
 
-    // Optional world wrap (torus), based on center
-    let wrap_mode = params.world_wrap_mode.round() as i32;
-    if wrap_mode == 1 {
-        let w = params.world_w.max(1.0).round();
-        let mut left = s.x.round();
-        let right = left + s.w.round();
-        if left < 0.0 {
-            left = w - s.w.round();
-        } else if right > w {
-            left = 0.0;
-        }
-        s.x = left;
-    } else if wrap_mode == 2 {
-        let w = params.world_w.max(1.0);
-        let center_x = s.x + 0.5 * s.w;
-        let wrapped = ((center_x % w) + w) % w;
-        s.x = (wrapped - 0.5 * s.w).round();
+    (s.ground_normal_x, s.ground_normal_y) = match (now_grounded, aabb_ground, obb_normal) {
+        (false, _, _) => (0.0, 0.0),
+        (true, false, Some(n)) => n,
+        _ => (0.0, -1.0),
+    };
+
+    // Pushing: holding into a wall that stopped all horizontal movement
+    let probe = Rect { x: rect.x + move_dir as f32, ..rect };
+    let blocked = rect.x == rect_start_x && any_intersect(world, probe);
+    s.pushing = if move_dir != 0 && now_grounded && blocked { move_dir as i8 } else { 0 };
+    s.just_landed = (now_grounded && !was_grounded) as u8;
+
+    // Scoring metrics
+    if now_grounded {
+        if !was_grounded {
+            s.max_airtime_frames = s.max_airtime_frames.max(s.airtime_frames);
+        }
+        s.airtime_frames = 0;
+        s.fall_distance = 0.0;
+        s.jump_cut_timer = 0;
+    } else {
+        s.airtime_frames = s.airtime_frames.saturating_add(1);
+        let before = s.fall_distance;
+        s.fall_distance += (s.y - start_y).max(0.0);
+        let limit = params.max_fall_distance;
+        if limit > 0.0 && before < limit && s.fall_distance >= limit && report.contains(EventMask::FELL_TOO_FAR) {
+            ev.fell_too_far = 1;
+        }
+    }
+    // The pre-collision vy covers the landing frame, where collision has already zeroed vy
+    s.max_fall_speed = s.max_fall_speed.max(s.vy).max(ev.vy_pre_collision);
+    if s.jump_active != 0 {
+        s.last_jump_apex_height = s.last_jump_apex_height.max(s.jump_launch_y - s.y);
+        if now_grounded && !jumped {
+            s.jump_active = 0;
+        }
+    }
+
+    // Last line of defence: never leave non-finite values in State
+    if !s.x.is_finite() || !s.y.is_finite() {
+        s.x = if start_x.is_finite() { start_x } else { 0.0 };
+        s.y = if start_y.is_finite() { start_y } else { 0.0 };
+        ev.numeric_fault = 1;
+    }
+    if !s.coyote.is_finite() || !s.jump_buffer.is_finite() {
+        s.coyote = 0.0;
+        s.jump_buffer = 0.0;
+        ev.numeric_fault = 1;
     }
 
     ev
 }
 
+/// Moves `state` to `(x, y)` and clears every transient field (velocity,
+/// timers, edge/jump tracking, grounding) while keeping the size, hitbox layout
+/// and scoring metrics. The player is airborne until the next `step` grounds it.
+pub fn respawn(state: &mut State, x: f32, y: f32) {
+    *state = State {
+        x,
+        y,
+        w: state.w,
+        h: state.h,
+        hitbox_offset_x: state.hitbox_offset_x,
+        hitbox_offset_y: state.hitbox_offset_y,
+        hitbox_w: state.hitbox_w,
+        hitbox_h: state.hitbox_h,
+        max_airtime_frames: state.max_airtime_frames,
+        max_fall_speed: state.max_fall_speed,
+        distance_x: state.distance_x,
+        ..State::default()
+    };
+}
+
+/// Predicts where the player lands by stepping a copy of `state` forward with
+/// `buttons` held constant every frame (JUMP held counts as a single press).
+///
+/// Returns the landing `(x, y, frames)` for the first frame that ends grounded,
+/// or `None` if the player is still airborne after `max_frames`. `state` is not
+/// modified.
+pub fn predict_landing(
+    params: &Params,
+    world: &[Rect],
+    state: &State,
+    buttons: Buttons,
+    max_frames: u32,
+) -> Option<(f32, f32, u32)> {
+    let mut s = *state;
+    for frame in 1..=max_frames {
+        step(params, world, &mut s, buttons);
+        if s.grounded != 0 {
+            return Some((s.x, s.y, frame));
+        }
+    }
+    None
+}
+
+/// The arc `predict_landing` walks, for drawing: each frame's `(x, y)` after
+/// stepping a copy of `start` with `buttons` held (JUMP held counts as a single
+/// press).
+///
+/// Records at most `frames` samples and stops early after the first frame that
+/// touches something solid from above or below: it ends grounded or reports a
+/// bonk. That frame's position is the last sample. Walls don't end the arc;
+/// the body slides along them as in `step`.
+pub fn trajectory(params: &Params, world: &[Rect], start: State, buttons: Buttons, frames: usize) -> Vec<(f32, f32)> {
+    let mut s = start;
+    let mut arc = Vec::with_capacity(frames);
+    for _ in 0..frames {
+        let ev = step(params, world, &mut s, buttons);
+        arc.push((s.x, s.y));
+        if s.grounded != 0 || ev.bonked != 0 {
+            break;
+        }
+    }
+    arc
+}
+
+/// Longest run [`frames_to_reach_x`] simulates before giving up (one minute).
+pub const REACH_X_MAX_FRAMES: u32 = 60 * 60;
+/// Consecutive frames without horizontal progress after which
+/// [`frames_to_reach_x`] treats the player as blocked.
+const REACH_X_STALL_FRAMES: u32 = 10;
+
+/// Frames for the player to get from `state` to `target_x` (their `x` at or
+/// past it) holding LEFT or RIGHT toward it, plus RUN when `run` is set.
+///
+/// Simulates a copy of `state` with `step`, so acceleration, air control and
+/// walls all count. `Some(0)` if already there; `None` when horizontal
+/// progress stalls (a wall) or the target is still out of reach after
+/// [`REACH_X_MAX_FRAMES`].
+pub fn frames_to_reach_x<W: CollisionWorld + ?Sized>(
+    params: &Params,
+    world: &W,
+    state: &State,
+    target_x: f32,
+    run: bool,
+) -> Option<u32> {
+    let dir = sign(target_x - state.x);
+    if dir == 0.0 {
+        return Some(0);
+    }
+    let mut buttons = if dir > 0.0 { Buttons::RIGHT } else { Buttons::LEFT };
+    buttons.set(Buttons::RUN, run);
+
+    let mut s = *state;
+    let mut stalled = 0;
+    for frame in 1..=REACH_X_MAX_FRAMES {
+        let before = s.x;
+        step(params, world, &mut s, buttons);
+        if (s.x - target_x) * dir >= 0.0 {
+            return Some(frame);
+        }
+        stalled = if (s.x - before) * dir > 0.0 { 0 } else { stalled + 1 };
+        if stalled >= REACH_X_STALL_FRAMES {
+            return None;
+        }
+    }
+    None
+}
+
+/// Event counts over a run of frames, from [`step_repeat`].
+#[derive(Copy, Clone, Debug, Default)]
+pub struct EventSummary {
+    pub jumped: u32,
+    pub landed: u32,
+    pub bonked: u32,
+    /// Events of the final frame (all zero when no frames ran).
+    pub last: Events,
+}
+
+/// Steps `s` forward `n` frames with `buttons` held constant (JUMP held counts
+/// as a single press), tallying the events.
+pub fn step_repeat<W: CollisionWorld + ?Sized>(
+    params: &Params,
+    world: &W,
+    s: &mut State,
+    buttons: Buttons,
+    n: usize,
+) -> EventSummary {
+    let mut summary = EventSummary::default();
+    for _ in 0..n {
+        let ev = step(params, world, s, buttons);
+        summary.jumped += ev.jumped as u32;
+        summary.landed += ev.landed as u32;
+        summary.bonked += ev.bonked as u32;
+        summary.last = ev;
+    }
+    summary
+}
+
+/// Steps `s` with `input_fn(frame)` (frame counted from 0) until
+/// `predicate(&events, &state)` holds after a frame, or `max_frames` have run.
+/// Returns the frames run and the last frame's events (`Events::default()`
+/// when none ran).
+pub fn step_until<W: CollisionWorld + ?Sized>(
+    params: &Params,
+    world: &W,
+    s: &mut State,
+    mut input_fn: impl FnMut(u32) -> Buttons,
+    mut predicate: impl FnMut(&Events, &State) -> bool,
+    max_frames: u32,
+) -> (u32, Events) {
+    let mut last = Events::default();
+    for frame in 0..max_frames {
+        last = step(params, world, s, input_fn(frame));
+        if predicate(&last, s) {
+            return (frame + 1, last);
+        }
+    }
+    (max_frames, last)
+}
+
 #[cfg(test)]
 mod tests {
-    use super::{step, Buttons, Params, Rect, State};
+    use super::{
+        depenetrate, diff_state, frames_to_reach_x, in_world_bounds, min_ground_rect, penetration, predict_landing, probe_move, rects_in_view, rects_in_view_wrapped,
+        rects_intersect, respawn, step, step_ext, step_input, step_profiled, step_pure, step_repeat, step_scaled, step_until,
+        step_with_events, step_with_obbs, step_with_substeps, step_with_surfaces, sweep, swept_aabb, trajectory,
+        wrap_pygame_legacy, Buttons, EventMask, Events, Face, GroundReason, Input, JumpKind, ObbRect,
+        world_bounds, Params, ParamsWarning, ParseButtonsError, Rect, State, SimEvent, StepStats, SurfaceBehavior, DT,
+        MAX_UPWARD_SPEED, UNBOUNDED_WORLD_H,
+    };
+    use crate::testing::{random_world, Rng};
 
     fn approx_eq(a: f32, b: f32) {
         let eps = 1e-4;
@@ -357,10 +2109,38 @@ mod tests {
         );
     }
 
+    #[test]
+    fn far_from_origin_moves_like_near_origin() {
+        // Positions are snapped to whole pixels every frame, which f32 holds
+        // exactly up to 2^24 px: a run 8M px out matches one at the origin.
+        let params = Params { world_wrap_mode: 0.0, ..Params::default() };
+        let run = |x0: f32| {
+            let world = [Rect { x: x0 - 1000.0, y: 480.0, w: 20_000.0, h: 60.0 }];
+            let mut s = State { x: x0, y: 480.0 - 44.0, w: 28.0, h: 44.0, grounded: 1, ..State::default() };
+            let mut path = Vec::new();
+            for frame in 0..600 {
+                let buttons = match frame % 150 {
+                    0..=59 => Buttons::RIGHT,
+                    60..=69 => Buttons::RIGHT | Buttons::JUMP,
+                    70..=119 => Buttons::LEFT | Buttons::RUN,
+                    _ => Buttons::empty(),
+                };
+                step(&params, &world, &mut s, buttons);
+                path.push((s.x - x0, s.y, s.vx, s.vy, s.grounded));
+            }
+            path
+        };
+        let near = run(100.0);
+        let far = run(8_000_000.0);
+        assert_eq!(near, far);
+    }
+
     #[test]
     fn deterministic_fixed_input_sequence_180_frames() {
-        let mut params = Params::default();
-        params.world_w = 960.0;
+        let params = Params {
+            world_w: 960.0,
+            ..Params::default()
+        };
 
         let world = [Rect {
             x: 0.0,
@@ -423,4 +2203,2404 @@ mod tests {
         assert_eq!(bonked, 0);
         assert_eq!(trace_hash, 0x94db7b2925cfad14);
     }
+
+    #[test]
+    fn sweep_projectile_reports_wall_index_and_face() {
+        let world = [
+            Rect { x: 0.0, y: 480.0, w: 960.0, h: 60.0 },
+            Rect { x: 300.0, y: 380.0, w: 20.0, h: 100.0 },
+        ];
+        let bullet = Rect { x: 200.0, y: 420.0, w: 8.0, h: 4.0 };
+
+        let hit = sweep(bullet, 150.0, 0.0, &world);
+        assert_eq!(hit.hit_index, Some(1));
+        assert_eq!(hit.face, Some(Face::Left));
+        approx_eq(hit.rect.x, 292.0);
+        approx_eq(hit.rect.y, 420.0);
+
+        let miss = sweep(bullet, 48.0, 0.0, &world);
+        assert_eq!(miss.hit_index, None);
+        assert_eq!(miss.face, None);
+        approx_eq(miss.rect.x, 248.0);
+    }
+
+    #[test]
+    fn sweep_falling_projectile_hits_floor_top() {
+        let world = [
+            Rect { x: 300.0, y: 380.0, w: 20.0, h: 100.0 },
+            Rect { x: 0.0, y: 480.0, w: 960.0, h: 60.0 },
+        ];
+        let shell = Rect { x: 100.0, y: 400.0, w: 6.0, h: 6.0 };
+
+        let hit = sweep(shell, 10.0, 200.0, &world);
+        assert_eq!(hit.hit_index, Some(1));
+        assert_eq!(hit.face, Some(Face::Top));
+        approx_eq(hit.rect.y, 474.0);
+    }
+
+    #[test]
+    fn bounce_wrap_mode_reflects_off_right_edge() {
+        let params = Params {
+            world_w: 960.0,
+            world_wrap_mode: 3.0,
+            world_bounce_restitution: 0.5,
+            ..Params::default()
+        };
+
+        let mut state = State {
+            x: 930.0,
+            y: 100.0,
+            vx: 200.0,
+            w: 28.0,
+            h: 44.0,
+            ..State::default()
+        };
+
+        step(&params, &[], &mut state, Buttons::empty());
+        approx_eq(state.x, 960.0 - 28.0);
+        approx_eq(state.vx, -100.0);
+
+        // Next frame moves away from the edge instead of wrapping.
+        step(&params, &[], &mut state, Buttons::empty());
+        assert!(state.x < 960.0 - 28.0);
+        assert!(state.vx < 0.0);
+    }
+
+    #[test]
+    fn time_scale_half_halves_per_frame_displacement() {
+        let params = Params {
+            world_wrap_mode: 0.0,
+            air_drag: 0.0,
+            ..Params::default()
+        };
+
+        let start = State {
+            x: 100.0,
+            y: 100.0,
+            vx: 120.0,
+            w: 28.0,
+            h: 44.0,
+            ..State::default()
+        };
+
+        let mut full = start;
+        step_scaled(&params, &[], &mut full, Buttons::empty(), 1.0);
+        let mut half = start;
+        step_scaled(&params, &[], &mut half, Buttons::empty(), 0.5);
+
+        approx_eq(full.x - start.x, 2.0);
+        approx_eq(half.x - start.x, 1.0);
+
+        let mut plain = start;
+        step(&params, &[], &mut plain, Buttons::empty());
+        assert_eq!(plain.x.to_bits(), full.x.to_bits());
+        assert_eq!(plain.vy.to_bits(), full.vy.to_bits());
+    }
+
+    #[test]
+    fn default_params_validate_clean_and_list_every_field() {
+        let params = Params::default();
+        assert_eq!(params.validate(), Ok(()));
+        assert_eq!(
+            params.named_fields().len() * 4,
+            std::mem::size_of::<Params>()
+        );
+    }
+
+    #[test]
+    fn named_fields_pairs_every_field_with_its_name() {
+        // Each field gets its own value through the struct, so a `PARAM_NAMES` out of
+        // step with the declaration shows up as a name paired with another field's value.
+        macro_rules! numbered {
+            ($($field:ident),* $(,)?) => {{
+                let mut n = 0.0;
+                let params = Params { $($field: { n += 1.0; n }),* };
+                let mut m = 0.0;
+                (params, [$((stringify!($field), { m += 1.0; m })),*])
+            }};
+        }
+        let (params, expected) = numbered!(
+            ground_max_speed, ground_accel, ground_decel, ground_friction, run_multiplier,
+            air_max_speed, air_accel, air_decel, air_drag, gravity_up, gravity_down,
+            terminal_velocity, fast_fall_multiplier, jump_velocity, jump_cut_multiplier,
+            coyote_time, jump_buffer, snap_to_ground, max_step_px, world_w, world_wrap_mode,
+            world_bounce_restitution, gravity_blend, jump_snap_suppress_frames,
+            air_max_speed_falling, air_accel_falling, noclip, max_substeps, run_ramp_time,
+            disabled_events, integration_mode, max_rise_speed, max_fall_distance, coyote_frames,
+            jump_buffer_frames, freeze_axes, min_jump_velocity, input_deadzone, input_smoothing,
+            wall_proximity_gravity_scale, auto_fast_fall_after, grounded_grace_frames,
+            max_step_px_x, max_step_px_y, world_h, foot_width_fraction, min_substeps, min_max_step,
+            auto_bounce, bonk_restitution, jump_cut_gravity_boost, jump_cut_boost_frames,
+        );
+
+        let fields = params.named_fields();
+        assert_eq!(fields.len(), expected.len());
+        for (name, value) in fields {
+            let want = expected.iter().find(|(n, _)| *n == name).map(|&(_, v)| v);
+            assert_eq!(Some(value), want, "{name}");
+        }
+        let nan_air = Params { air_max_speed: f32::NAN, ..Params::default() };
+        assert_eq!(nan_air.validate(), Err(vec![ParamsWarning::NonFinite("air_max_speed")]));
+    }
+
+    #[test]
+    fn validate_flags_each_warning() {
+        let cases = [
+            (
+                Params { ground_accel: f32::NAN, ..Params::default() },
+                ParamsWarning::NonFinite("ground_accel"),
+            ),
+            (
+                Params { jump_velocity: MAX_UPWARD_SPEED + 1.0, ..Params::default() },
+                ParamsWarning::JumpVelocityAboveMaxUpwardSpeed,
+            ),
+            (
+                Params { terminal_velocity: MAX_UPWARD_SPEED * 0.95, ..Params::default() },
+                ParamsWarning::TerminalVelocityNearClamp,
+            ),
+            (
+                Params { terminal_velocity: 0.0, ..Params::default() },
+                ParamsWarning::TerminalVelocityNotPositive,
+            ),
+            (
+                Params { max_step_px: 0.25, ..Params::default() },
+                ParamsWarning::MaxStepBelowMinimum,
+            ),
+            (
+                Params { jump_cut_multiplier: 1.5, ..Params::default() },
+                ParamsWarning::JumpCutMultiplierOutOfRange,
+            ),
+            (
+                Params { world_wrap_mode: 7.0, ..Params::default() },
+                ParamsWarning::UnknownWrapMode,
+            ),
+        ];
+
+        for (params, expected) in cases {
+            assert_eq!(params.validate(), Err(vec![expected]), "{expected:?}");
+        }
+    }
+
+    #[test]
+    fn overlapping_rects_resolve_independent_of_world_order() {
+        // A double-thick wall split into two adjacent rects. The player starts
+        // embedded in the left half (e.g. after a resize) and moves left.
+        let a = Rect { x: 300.0, y: 0.0, w: 20.0, h: 100.0 };
+        let b = Rect { x: 320.0, y: 0.0, w: 20.0, h: 100.0 };
+        let player = Rect { x: 296.0, y: 20.0, w: 20.0, h: 40.0 };
+
+        // The old single pass over [b, a] pushed the player out of `a` into `b`.
+        for world in [[a, b], [b, a]] {
+            let hit = sweep(player, -2.0, 0.0, &world);
+            approx_eq(hit.rect.x, 280.0);
+            assert_eq!(hit.face, Some(Face::Left));
+            assert!(world.iter().all(|p| !rects_intersect(&hit.rect, p)));
+        }
+    }
+
+    #[test]
+    fn gravity_blend_smooths_apex_acceleration() {
+        // Largest frame-to-frame change in vertical acceleration across a jump.
+        fn max_accel_jump(blend: f32) -> f32 {
+            let params = Params {
+                gravity_blend: blend,
+                world_wrap_mode: 0.0,
+                ..Params::default()
+            };
+            let mut state = State {
+                x: 100.0,
+                y: 400.0,
+                vy: -params.jump_velocity,
+                w: 28.0,
+                h: 44.0,
+                ..State::default()
+            };
+
+            let mut prev_dvy: Option<f32> = None;
+            let mut worst = 0.0f32;
+            for _ in 0..40 {
+                let vy0 = state.vy;
+                step(&params, &[], &mut state, Buttons::empty());
+                let dvy = state.vy - vy0;
+                if let Some(prev) = prev_dvy {
+                    worst = worst.max((dvy - prev).abs());
+                }
+                prev_dvy = Some(dvy);
+            }
+            worst
+        }
+
+        let p = Params::default();
+        let hard = max_accel_jump(0.0);
+        let smooth = max_accel_jump(1.0);
+        approx_eq(hard, (p.gravity_down - p.gravity_up) * DT);
+        assert!(smooth < 1.0, "blend 1 still has an apex step of {smooth}");
+    }
+
+    #[test]
+    fn predict_landing_ballistic_drop_without_mutating_state() {
+        let params = Params {
+            world_wrap_mode: 0.0,
+            ..Params::default()
+        };
+        let world = [Rect { x: 0.0, y: 480.0, w: 960.0, h: 60.0 }];
+        let state = State {
+            x: 100.0,
+            y: 200.0,
+            vx: 120.0,
+            w: 28.0,
+            h: 44.0,
+            ..State::default()
+        };
+
+        let (x, y, frames) = predict_landing(&params, &world, &state, Buttons::empty(), 600)
+            .expect("should land");
+
+        // Reference: step the same body by hand.
+        let mut manual = state;
+        let mut n = 0;
+        while manual.grounded == 0 {
+            step(&params, &world, &mut manual, Buttons::empty());
+            n += 1;
+        }
+        assert_eq!(frames, n);
+        approx_eq(x, manual.x);
+        approx_eq(y, 480.0 - 44.0);
+        assert!(x > state.x);
+        approx_eq(state.y, 200.0);
+
+        assert_eq!(predict_landing(&params, &world, &state, Buttons::empty(), 3), None);
+    }
+
+    #[test]
+    fn slow_takeoff_is_not_resnapped_to_ground() {
+        // 240 px/s rises 4px on the first frame, inside the 6px snap probe.
+        let params = Params {
+            jump_velocity: 240.0,
+            world_wrap_mode: 0.0,
+            ..Params::default()
+        };
+        let world = [Rect { x: 0.0, y: 480.0, w: 960.0, h: 60.0 }];
+        let mut state = State {
+            x: 100.0,
+            y: 480.0 - 44.0,
+            w: 28.0,
+            h: 44.0,
+            grounded: 1,
+            ..State::default()
+        };
+
+        let ev = step(&params, &world, &mut state, Buttons::JUMP);
+        assert_eq!(ev.jumped, 1);
+        assert_eq!(ev.landed, 0);
+        assert_eq!(state.grounded, 0);
+        assert!(state.y < 480.0 - 44.0);
+
+        for _ in 0..3 {
+            let ev = step(&params, &world, &mut state, Buttons::JUMP);
+            assert_eq!(ev.landed, 0);
+            assert_eq!(state.grounded, 0);
+        }
+
+        // Walking off a ledge is unaffected.
+        let ledge = [Rect { x: 0.0, y: 480.0, w: 120.0, h: 60.0 }];
+        let mut walker = State {
+            x: 90.0,
+            y: 480.0 - 44.0,
+            vx: 200.0,
+            w: 28.0,
+            h: 44.0,
+            grounded: 1,
+            ..State::default()
+        };
+        let mut frames = 0;
+        while walker.grounded != 0 && frames < 30 {
+            step(&params, &ledge, &mut walker, Buttons::RIGHT);
+            frames += 1;
+        }
+        assert_eq!(walker.grounded, 0);
+        assert!(walker.x >= 120.0);
+    }
+
+    #[test]
+    fn diff_state_lists_differing_fields() {
+        let a = State { x: 10.0, vx: 120.0, grounded: 1, airtime_frames: 3, ..State::default() };
+        let b = State { x: 10.0 + 1e-6, vx: -40.0, grounded: 0, ..a };
+        assert_eq!(diff_state(&a, &b), [("vx", 120.0, -40.0), ("grounded", 1.0, 0.0)]);
+        assert!(diff_state(&a, &a).is_empty());
+
+        let nan = State { vy: f32::NAN, ..a };
+        assert!(diff_state(&nan, &nan).is_empty());
+        assert_eq!(diff_state(&a, &nan).len(), 1);
+    }
+
+    #[test]
+    fn world_bounds_contains_inside_rects_only() {
+        let params = Params { world_w: 960.0, world_h: 540.0, ..Params::default() };
+        assert_eq!(format!("{:?}", world_bounds(&params)), format!("{:?}", Rect { x: 0.0, y: 0.0, w: 960.0, h: 540.0 }));
+
+        let inside = Rect { x: 100.0, y: 200.0, w: 28.0, h: 44.0 };
+        let flush = Rect { x: 932.0, y: 496.0, w: 28.0, h: 44.0 };
+        let straddle_x = Rect { x: 940.0, ..inside };
+        let straddle_y = Rect { y: -10.0, ..inside };
+        assert!(in_world_bounds(&params, &inside));
+        assert!(in_world_bounds(&params, &flush));
+        assert!(!in_world_bounds(&params, &straddle_x));
+        assert!(!in_world_bounds(&params, &straddle_y));
+
+        // world_h == 0: no vertical limit, horizontal still applies.
+        let open = Params { world_h: 0.0, ..params };
+        assert_eq!(world_bounds(&open).h, UNBOUNDED_WORLD_H);
+        assert!(in_world_bounds(&open, &straddle_y));
+        assert!(in_world_bounds(&open, &Rect { y: 100_000.0, ..inside }));
+        assert!(!in_world_bounds(&open, &straddle_x));
+    }
+
+    #[test]
+    fn frames_to_reach_x_counts_acceleration_and_stops_at_walls() {
+        // 3600 px/s^2 up to 240 px/s moves 1, 2, 3, 4 px, then 4 px every frame:
+        // 10 px after 4 frames, then 23 more frames to cover the other 90.
+        let params = Params {
+            world_wrap_mode: 0.0,
+            ground_accel: 3600.0,
+            ground_max_speed: 240.0,
+            ..Params::default()
+        };
+        let floor = [Rect { x: 0.0, y: 480.0, w: 960.0, h: 60.0 }];
+        let start = State { x: 200.0, y: 480.0 - 44.0, w: 28.0, h: 44.0, grounded: 1, ..State::default() };
+
+        assert_eq!(frames_to_reach_x(&params, &floor[..], &start, 300.0, false), Some(27));
+        assert_eq!(frames_to_reach_x(&params, &floor[..], &start, 100.0, false), Some(27));
+        assert_eq!(frames_to_reach_x(&params, &floor[..], &start, 200.0, false), Some(0));
+        let running = frames_to_reach_x(&params, &floor[..], &start, 300.0, true).unwrap();
+        assert!(running < 27, "{running}");
+
+        let walled = [floor[0], Rect { x: 260.0, y: 300.0, w: 20.0, h: 180.0 }];
+        assert_eq!(frames_to_reach_x(&params, &walled[..], &start, 300.0, false), None);
+    }
+
+    #[test]
+    fn step_repeat_matches_manual_loop() {
+        let params = Params { world_wrap_mode: 0.0, ..Params::default() };
+        let world = [
+            Rect { x: 0.0, y: 480.0, w: 960.0, h: 60.0 },
+            Rect { x: 0.0, y: 200.0, w: 960.0, h: 20.0 },
+        ];
+        let start = State { x: 100.0, y: 300.0, w: 28.0, h: 44.0, ..State::default() };
+
+        for buttons in [Buttons::RIGHT, Buttons::RIGHT | Buttons::JUMP] {
+            let mut manual = start;
+            let (mut jumped, mut landed, mut bonked, mut last) = (0, 0, 0, Events::default());
+            for _ in 0..60 {
+                last = step(&params, &world, &mut manual, buttons);
+                jumped += last.jumped as u32;
+                landed += last.landed as u32;
+                bonked += last.bonked as u32;
+            }
+
+            let mut repeated = start;
+            let summary = step_repeat(&params, &world[..], &mut repeated, buttons, 60);
+            assert_eq!((summary.jumped, summary.landed, summary.bonked), (jumped, landed, bonked));
+            assert_eq!(format!("{:?}", summary.last), format!("{last:?}"));
+            assert_eq!(format!("{repeated:?}"), format!("{manual:?}"));
+            assert!(landed >= 1);
+        }
+
+        let mut idle = start;
+        let none = step_repeat(&params, &world[..], &mut idle, Buttons::RIGHT, 0);
+        assert_eq!((none.jumped, none.landed, none.bonked, idle.x), (0, 0, 0, start.x));
+    }
+
+    #[test]
+    fn step_with_events_queues_typed_events_in_order() {
+        let params = Params { world_wrap_mode: 3.0, world_w: 960.0, ..Params::default() };
+        let floor = [Rect { x: 0.0, y: 480.0, w: 960.0, h: 60.0 }];
+
+        // Lands and hits the right edge in the same frame: landing, then the bounce.
+        let mut s = State { x: 930.0, y: 430.0, vx: 220.0, vy: 300.0, w: 28.0, h: 44.0, ..State::default() };
+        let mut out = Vec::new();
+        let ev = step_with_events(&params, &floor, &mut s, Buttons::RIGHT, &mut out);
+        assert_eq!(ev.landed, 1);
+        assert_eq!(out.len(), 2, "{out:?}");
+        assert_eq!(out[0], SimEvent::Landed { speed: ev.vy_pre_collision });
+        assert_eq!(out[1], SimEvent::Bounced { vx: s.vx });
+        assert!(s.vx < 0.0);
+
+        // Jumping into a low ceiling; the queue appends.
+        let room = [floor[0], Rect { x: 0.0, y: 420.0, w: 960.0, h: 12.0 }];
+        let mut s = State { x: 100.0, y: 480.0 - 44.0, w: 28.0, h: 44.0, grounded: 1, ..State::default() };
+        step_with_events(&params, &room, &mut s, Buttons::JUMP, &mut out);
+        assert_eq!(
+            out[2..],
+            [
+                SimEvent::Jumped { kind: JumpKind::Ground },
+                SimEvent::Bonked { speed: params.jump_velocity },
+                SimEvent::LeftGround,
+            ]
+        );
+
+        // Same physics and Events as plain `step`.
+        let mut plain = State { x: 100.0, y: 480.0 - 44.0, w: 28.0, h: 44.0, grounded: 1, ..State::default() };
+        let plain_ev = step(&params, &room, &mut plain, Buttons::JUMP);
+        assert_eq!(format!("{plain:?}"), format!("{s:?}"));
+        assert_eq!((plain_ev.jumped, plain_ev.bonked, plain_ev.left_ground), (1, 1, 1));
+    }
+
+    #[test]
+    fn events_report_bonked_and_wall_rect_indices() {
+        let params = Params { world_wrap_mode: 0.0, ..Params::default() };
+        let world = [
+            Rect { x: 0.0, y: 480.0, w: 960.0, h: 60.0 },
+            Rect { x: 0.0, y: 420.0, w: 100.0, h: 12.0 },
+            Rect { x: 100.0, y: 420.0, w: 200.0, h: 12.0 },
+            Rect { x: 500.0, y: 300.0, w: 40.0, h: 180.0 },
+        ];
+        let standing = State { x: 150.0, y: 480.0 - 44.0, w: 28.0, h: 44.0, grounded: 1, ..State::default() };
+
+        let mut s = standing;
+        let ev = step(&params, &world, &mut s, Buttons::JUMP);
+        assert_eq!((ev.bonked, ev.bonk_index, ev.wall_index), (1, 2, -1));
+
+        // Run right into the wall; frames before the contact report no indices.
+        let mut s = State { x: 400.0, ..standing };
+        let hit = (0..120)
+            .map(|_| step(&params, &world, &mut s, Buttons::RIGHT))
+            .inspect(|ev| assert_eq!(ev.bonk_index, -1))
+            .find(|ev| ev.wall_index != -1)
+            .unwrap();
+        assert_eq!(hit.wall_index, 3);
+        assert_eq!(s.x + s.w, 500.0);
+
+        let quiet = Events::default();
+        assert_eq!((quiet.bonk_index, quiet.wall_index), (-1, -1));
+    }
+
+    #[test]
+    fn bonk_restitution_rebounds_downward() {
+        let params = Params { world_wrap_mode: 0.0, ..Params::default() };
+        let room = [
+            Rect { x: 0.0, y: 480.0, w: 960.0, h: 60.0 },
+            Rect { x: 0.0, y: 420.0, w: 960.0, h: 12.0 },
+        ];
+        let standing = State { x: 100.0, y: 480.0 - 44.0, w: 28.0, h: 44.0, grounded: 1, ..State::default() };
+
+        let bouncy = Params { bonk_restitution: 0.5, ..params };
+        let mut s = standing;
+        let mut out = Vec::new();
+        let ev = step_with_events(&bouncy, &room, &mut s, Buttons::JUMP, &mut out);
+        assert_eq!((ev.bonked, s.vy), (1, 0.5 * params.jump_velocity));
+        assert!(out.contains(&SimEvent::BouncedDown { vy: s.vy }), "{out:?}");
+
+        // The default of 0 is the classic bonk: upward speed stops dead.
+        assert_eq!(params.bonk_restitution, 0.0);
+        let mut s = standing;
+        out.clear();
+        let ev = step_with_events(&params, &room, &mut s, Buttons::JUMP, &mut out);
+        assert_eq!((ev.bonked, s.vy.to_bits()), (1, 0.0f32.to_bits()));
+        assert!(!out.iter().any(|e| matches!(e, SimEvent::BouncedDown { .. })));
+    }
+
+    #[test]
+    fn step_until_stops_on_first_landing() {
+        let params = Params { world_wrap_mode: 0.0, ..Params::default() };
+        let floor = [Rect { x: 0.0, y: 480.0, w: 960.0, h: 60.0 }];
+        let drop = State { x: 100.0, y: 200.0, w: 28.0, h: 44.0, ..State::default() };
+
+        let mut s = drop;
+        let mut asked = Vec::new();
+        let (frames, ev) = step_until(
+            &params,
+            &floor[..],
+            &mut s,
+            |frame| {
+                asked.push(frame);
+                Buttons::RIGHT
+            },
+            |ev, _| ev.landed != 0,
+            600,
+        );
+        let (x, y, expected) = predict_landing(&params, &floor, &drop, Buttons::RIGHT, 600).unwrap();
+        assert_eq!((frames, ev.landed, s.x, s.y), (expected, 1, x, y));
+        assert_eq!(asked, (0..frames).collect::<Vec<_>>());
+
+        // Hitting the cap returns it with the last frame's events.
+        let mut s = drop;
+        let (frames, ev) = step_until(&params, &floor[..], &mut s, |_| Buttons::empty(), |_, s| s.y < 0.0, 5);
+        assert_eq!((frames, ev.landed, s.grounded), (5, 0, 0));
+    }
+
+    #[test]
+    fn jump_kind_reports_ground_and_coyote_jumps() {
+        let params = Params { world_wrap_mode: 0.0, ..Params::default() };
+        let ledge = [Rect { x: 0.0, y: 400.0, w: 100.0, h: 20.0 }];
+        let standing = State { x: 40.0, y: 400.0 - 44.0, w: 28.0, h: 44.0, grounded: 1, ..State::default() };
+
+        let mut s = standing;
+        let ev = step(&params, &ledge, &mut s, Buttons::JUMP);
+        assert_eq!((ev.jumped, ev.jump_kind), (1, JumpKind::Ground as u8));
+        let ev = step(&params, &ledge, &mut s, Buttons::JUMP);
+        assert_eq!((ev.jumped, ev.jump_kind), (0, JumpKind::None as u8));
+
+        // Walk off the lip, then jump inside the coyote window.
+        let mut s = State { x: 98.0, vx: 260.0, ..standing };
+        assert_eq!(step(&params, &ledge, &mut s, Buttons::RIGHT).left_ground, 1);
+        let ev = step(&params, &ledge, &mut s, Buttons::RIGHT | Buttons::JUMP);
+        assert_eq!((ev.jumped, ev.jump_kind), (1, JumpKind::Coyote as u8));
+
+        // grounded_grace_frames jumps count as coyote jumps too.
+        let grace = Params { coyote_time: 0.0, grounded_grace_frames: 2.0, ..params };
+        let mut s = standing;
+        step(&grace, &[], &mut s, Buttons::empty());
+        let ev = step(&grace, &[], &mut s, Buttons::JUMP);
+        assert_eq!((ev.jumped, ev.jump_kind), (1, JumpKind::Coyote as u8));
+
+        // Disabling JUMPED silences the kind along with the flag.
+        let quiet = Params { disabled_events: EventMask::JUMPED.bits() as f32, ..params };
+        let mut s = standing;
+        let ev = step(&quiet, &ledge, &mut s, Buttons::JUMP);
+        assert_eq!((ev.jumped, ev.jump_kind, s.vy < 0.0), (0, JumpKind::None as u8, true));
+    }
+
+    #[test]
+    fn min_ground_rect_matches_ground_check() {
+        let grounded_on = |params: &Params, ledge: Rect| {
+            let mut s = State { x: 100.0, y: 400.0, w: 28.0, h: 44.0, ..State::default() };
+            for _ in 0..30 {
+                step(params, &[ledge], &mut s, Buttons::empty());
+            }
+            s.grounded == 1
+        };
+        for fraction in [1.0, 0.5, 0.25] {
+            let params = Params { world_wrap_mode: 0.0, foot_width_fraction: fraction, ..Params::default() };
+            let min = min_ground_rect(&params, 100.0, 28.0, 480.0);
+            assert_eq!((min.y, min.h), (480.0, 1.0));
+            assert_eq!(min.w, (28.0 * fraction).round(), "fraction {fraction}");
+            assert!(grounded_on(&params, min), "fraction {fraction}");
+
+            // Ledges reaching in from either side only count once they enter the foot span.
+            let left = |right_edge: f32| Rect { x: 0.0, y: 480.0, w: right_edge, h: 20.0 };
+            let right = |left_edge: f32| Rect { x: left_edge, y: 480.0, w: 400.0 - left_edge, h: 20.0 };
+            assert!(grounded_on(&params, left(min.x + 1.0)));
+            assert!(grounded_on(&params, right(min.x + min.w - 1.0)));
+            if fraction < 1.0 {
+                assert!(!grounded_on(&params, left(min.x)), "fraction {fraction}");
+                assert!(!grounded_on(&params, right(min.x + min.w)), "fraction {fraction}");
+            }
+        }
+    }
+
+    #[test]
+    fn standing_sunk_into_ledge_is_not_shoved_sideways() {
+        let params = Params { world_wrap_mode: 0.0, ..Params::default() };
+        let ledge = [Rect { x: 100.0, y: 480.0, w: 200.0, h: 20.0 }];
+
+        // Only 3px of the hitbox is over the ledge, but the feet are 4px into it:
+        // the shortest way out is sideways, off the ledge.
+        for sunk in [0.0, 1.0, 4.0] {
+            let mut s = State { x: 75.0, y: 480.0 - 44.0 + sunk, w: 28.0, h: 44.0, grounded: 1, ..State::default() };
+            for frame in 0..120 {
+                step(&params, &ledge, &mut s, Buttons::empty());
+                assert_eq!((s.x, s.y, s.grounded), (75.0, 436.0, 1), "sunk {sunk}, frame {frame}");
+            }
+        }
+
+        // Deeper than the ground-snap distance it is a real overlap again.
+        let mut s = State { x: 75.0, y: 480.0 - 44.0 + 8.0, w: 28.0, h: 44.0, grounded: 1, ..State::default() };
+        step(&params, &ledge, &mut s, Buttons::empty());
+        assert_eq!(s.x, 72.0);
+    }
+
+    #[test]
+    fn narrow_foot_ignores_corner_only_ground() {
+        let params = Params { world_wrap_mode: 0.0, ..Params::default() };
+        let narrow = Params { foot_width_fraction: 0.5, ..params };
+        // The ledge ends 4px under the hitbox's left corner.
+        let corner = [Rect { x: 0.0, y: 480.0, w: 104.0, h: 20.0 }];
+        let wide = [Rect { x: 0.0, y: 480.0, w: 400.0, h: 20.0 }];
+        let start = State { x: 100.0, y: 400.0, w: 28.0, h: 44.0, ..State::default() };
+
+        let settle = |params: &Params, world: &[Rect]| {
+            let mut s = start;
+            let landed = (0..30).filter(|_| step(params, world, &mut s, Buttons::empty()).landed != 0).count();
+            (s.grounded, landed)
+        };
+        assert_eq!(settle(&params, &corner), (1, 1));
+        assert_eq!(settle(&narrow, &corner), (0, 0));
+        assert_eq!(settle(&narrow, &wide), (1, 1));
+
+        // Either way the full rect still collides: the body rests on the corner.
+        let mut s = start;
+        for _ in 0..30 {
+            step(&narrow, &corner, &mut s, Buttons::empty());
+        }
+        assert_eq!(s.y, 480.0 - 44.0);
+    }
+
+    #[test]
+    fn ground_reason_contact_for_landing_and_snap_for_step_down() {
+        let params = Params {
+            world_wrap_mode: 0.0,
+            ..Params::default()
+        };
+
+        // Flat landing: falling fast enough to reach the floor this frame.
+        let floor = [Rect { x: 0.0, y: 480.0, w: 960.0, h: 60.0 }];
+        let mut faller = State {
+            x: 100.0,
+            y: 480.0 - 44.0 - 2.0,
+            vy: 300.0,
+            w: 28.0,
+            h: 44.0,
+            ..State::default()
+        };
+        let mut landing = None;
+        for _ in 0..60 {
+            let ev = step(&params, &floor, &mut faller, Buttons::empty());
+            if ev.landed != 0 {
+                landing = Some(ev.ground_reason);
+                break;
+            }
+            assert_eq!(ev.ground_reason, GroundReason::None as u8);
+        }
+        assert_eq!(landing, Some(GroundReason::Contact as u8));
+
+        // Walking down a 4px step stays grounded via the snap probe.
+        let steps = [
+            Rect { x: 0.0, y: 480.0, w: 200.0, h: 60.0 },
+            Rect { x: 200.0, y: 484.0, w: 760.0, h: 60.0 },
+        ];
+        let mut walker = State {
+            x: 150.0,
+            y: 480.0 - 44.0,
+            w: 28.0,
+            h: 44.0,
+            grounded: 1,
+            ..State::default()
+        };
+        let mut reasons = Vec::new();
+        for _ in 0..40 {
+            let ev = step(&params, &steps, &mut walker, Buttons::RIGHT);
+            assert_eq!(walker.grounded, 1);
+            reasons.push(ev.ground_reason);
+        }
+        assert!(reasons.contains(&(GroundReason::Snap as u8)));
+        approx_eq(walker.y, 484.0 - 44.0);
+    }
+
+    #[test]
+    fn player_rests_on_rotated_block_without_sinking() {
+        let params = Params {
+            world_wrap_mode: 0.0,
+            ..Params::default()
+        };
+        let slope = ObbRect {
+            cx: 200.0,
+            cy: 400.0,
+            hw: 80.0,
+            hh: 20.0,
+            angle: 30f32.to_radians(),
+        };
+        let mut state = State {
+            x: 186.0,
+            y: 300.0,
+            w: 28.0,
+            h: 44.0,
+            ..State::default()
+        };
+
+        let mut grounded_frames = 0;
+        for _ in 0..90 {
+            step_with_obbs(&params, &[], &[slope], &mut state, Buttons::empty());
+            let player = Rect { x: state.x, y: state.y, w: state.w, h: state.h };
+            assert!(
+                super::obb::aabb_obb_mtv(&player, &slope).is_none(),
+                "player embedded in slope at ({}, {})",
+                state.x,
+                state.y
+            );
+            grounded_frames += state.grounded as u32;
+        }
+
+        assert!(grounded_frames > 0);
+        // Resting on the top face, having slid downhill (positive angle descends to the right).
+        let (sin, cos) = slope.angle.sin_cos();
+        let (top_x, top_y) = (slope.cx + slope.hh * sin, slope.cy - slope.hh * cos);
+        let surface_y = top_y + (state.x - top_x) * sin / cos;
+        assert!(state.y + state.h <= surface_y + 1.0);
+        assert!(state.y + state.h >= surface_y - 2.0);
+        assert!(state.x > 186.0);
+    }
+
+    #[test]
+    fn fast_falling_only_flags_descent_while_down_held() {
+        let params = Params {
+            world_wrap_mode: 0.0,
+            ..Params::default()
+        };
+        let world = [Rect { x: 0.0, y: 480.0, w: 960.0, h: 60.0 }];
+        let mut state = State {
+            x: 100.0,
+            y: 480.0 - 44.0,
+            w: 28.0,
+            h: 44.0,
+            grounded: 1,
+            ..State::default()
+        };
+
+        let mut saw_rise = false;
+        let mut saw_fast_fall = false;
+        for frame in 0..120 {
+            let buttons = if frame == 0 { Buttons::DOWN | Buttons::JUMP } else { Buttons::DOWN };
+            let vy_before = state.vy;
+            let ev = step(&params, &world, &mut state, buttons);
+            if state.grounded != 0 && frame > 0 {
+                break;
+            }
+            if vy_before <= 0.0 {
+                assert_eq!(ev.fast_falling, 0, "flagged while rising on frame {frame}");
+                saw_rise = true;
+            } else {
+                assert_eq!(ev.fast_falling, 1, "not flagged while falling on frame {frame}");
+                saw_fast_fall = true;
+            }
+        }
+        assert!(saw_rise && saw_fast_fall);
+    }
+
+    #[test]
+    fn buttons_parse_numeric_named_and_shorthand_forms() {
+        let lj = Buttons::LEFT | Buttons::JUMP;
+        assert_eq!("17".parse::<Buttons>(), Ok(lj));
+        assert_eq!("LEFT|JUMP".parse::<Buttons>(), Ok(lj));
+        assert_eq!("left + jump".parse::<Buttons>(), Ok(lj));
+        assert_eq!("LR J".parse::<Buttons>(), Ok(Buttons::LEFT | Buttons::RIGHT | Buttons::JUMP));
+        assert_eq!("B".parse::<Buttons>(), Ok(Buttons::RUN));
+        assert_eq!("".parse::<Buttons>(), Ok(Buttons::empty()));
+        assert_eq!("NONE".parse::<Buttons>(), Ok(Buttons::empty()));
+        assert_eq!("-".parse::<Buttons>(), Ok(Buttons::empty()));
+        assert_eq!("LEFT|FLY".parse::<Buttons>(), Err(ParseButtonsError("LEFT|FLY".into())));
+    }
+
+    #[test]
+    fn buttons_display_round_trips_every_combination() {
+        assert_eq!(Buttons::empty().to_string(), "NONE");
+        assert_eq!((Buttons::RIGHT | Buttons::JUMP).to_string(), "RIGHT|JUMP");
+        for bits in 0..32u8 {
+            let b = Buttons::from_bits_truncate(bits);
+            assert_eq!(b.to_string().parse::<Buttons>(), Ok(b), "{b}");
+        }
+    }
+
+    #[test]
+    fn falling_air_control_overrides_apply_only_while_descending() {
+        let params = Params {
+            air_accel: 1200.0,
+            air_accel_falling: 600.0,
+            air_max_speed_falling: 100.0,
+            world_wrap_mode: 0.0,
+            ..Params::default()
+        };
+        let airborne = State {
+            x: 100.0,
+            y: 100.0,
+            w: 28.0,
+            h: 44.0,
+            ..State::default()
+        };
+
+        let mut rising = State { vy: -300.0, ..airborne };
+        step(&params, &[], &mut rising, Buttons::RIGHT);
+        approx_eq(rising.vx, 1200.0 * DT);
+
+        let mut falling = State { vy: 300.0, ..airborne };
+        step(&params, &[], &mut falling, Buttons::RIGHT);
+        approx_eq(falling.vx, 600.0 * DT);
+
+        let mut fast = State { vx: 180.0, vy: 300.0, ..airborne };
+        step(&params, &[], &mut fast, Buttons::RIGHT);
+        approx_eq(fast.vx, 100.0);
+
+        // Negative (default) inherits the rising values.
+        let inherit = Params { air_accel_falling: -1.0, ..params };
+        let mut same = State { vy: 300.0, ..airborne };
+        step(&inherit, &[], &mut same, Buttons::RIGHT);
+        approx_eq(same.vx, 1200.0 * DT);
+    }
+
+    #[test]
+    fn non_finite_params_flag_fault_and_keep_state_finite() {
+        let params = Params {
+            jump_velocity: f32::NAN,
+            coyote_time: f32::INFINITY,
+            world_wrap_mode: 0.0,
+            ..Params::default()
+        };
+        let world = [Rect { x: 0.0, y: 480.0, w: 960.0, h: 60.0 }];
+        let mut state = State {
+            x: 100.0,
+            y: 480.0 - 44.0,
+            w: 28.0,
+            h: 44.0,
+            grounded: 1,
+            ..State::default()
+        };
+
+        let mut faults = 0;
+        for frame in 0..30 {
+            let buttons = if frame % 4 == 0 { Buttons::JUMP | Buttons::RIGHT } else { Buttons::RIGHT };
+            let ev = step(&params, &world, &mut state, buttons);
+            faults += ev.numeric_fault as u32;
+            for v in [state.x, state.y, state.vx, state.vy, state.coyote, state.jump_buffer] {
+                assert!(v.is_finite(), "non-finite state on frame {frame}: {state:?}");
+            }
+        }
+        assert!(faults > 0);
+
+        // Healthy params never report a fault.
+        let mut ok = State { grounded: 1, ..state };
+        let ev = step(&Params::default(), &world, &mut ok, Buttons::JUMP);
+        assert_eq!(ev.numeric_fault, 0);
+    }
+
+    #[test]
+    fn noclip_flies_through_solid_rect_without_gravity() {
+        let params = Params {
+            noclip: 1.0,
+            world_wrap_mode: 0.0,
+            ..Params::default()
+        };
+        let world = [Rect { x: 200.0, y: 0.0, w: 60.0, h: 540.0 }];
+        let mut state = State { x: 150.0, y: 300.0, w: 28.0, h: 44.0, ..State::default() };
+
+        for _ in 0..60 {
+            step(&params, &world, &mut state, Buttons::RIGHT);
+        }
+        assert!(state.x > 260.0, "should pass through the wall: {state:?}");
+        approx_eq(state.y, 300.0);
+        assert_eq!(state.grounded, 0);
+
+        // JUMP flies up, DOWN flies down.
+        step(&params, &world, &mut state, Buttons::JUMP);
+        assert!(state.y < 300.0);
+        step(&params, &world, &mut state, Buttons::DOWN);
+        step(&params, &world, &mut state, Buttons::DOWN);
+        assert!(state.y > 300.0);
+    }
+
+    #[test]
+    fn noclip_flies_with_analog_move_x() {
+        let params = Params { noclip: 1.0, world_wrap_mode: 0.0, ..Params::default() };
+        let start = State { x: 150.0, y: 300.0, w: 28.0, h: 44.0, ..State::default() };
+
+        let mut half = start;
+        step_input(&params, &[], &mut half, &Input { move_x: -0.5, ..Input::default() });
+        approx_eq(half.vx, -0.5 * params.ground_max_speed);
+        assert!(half.x < 150.0);
+        approx_eq(half.y, 300.0);
+
+        // Full deflection flies as fast as the buttons.
+        let (mut analog, mut bits) = (start, start);
+        step_input(&params, &[], &mut analog, &Input { move_x: 1.0, ..Input::default() });
+        step(&params, &[], &mut bits, Buttons::RIGHT);
+        assert_eq!((analog.x, analog.vx), (bits.x, bits.vx));
+    }
+
+    #[test]
+    fn noclip_still_wraps() {
+        let params = Params {
+            noclip: 1.0,
+            world_w: 960.0,
+            world_wrap_mode: 1.0,
+            ..Params::default()
+        };
+        let mut state = State { x: 2.0, y: 100.0, w: 28.0, h: 44.0, ..State::default() };
+        step(&params, &[], &mut state, Buttons::LEFT);
+        approx_eq(state.x, 960.0 - 28.0);
+    }
+
+    #[test]
+    fn step_with_substeps_reports_each_substep_and_matches_step() {
+        let params = Params { max_step_px: 4.0, world_wrap_mode: 0.0, ..Params::default() };
+        let world = [Rect { x: 0.0, y: 480.0, w: 960.0, h: 60.0 }];
+        let start = State { x: 100.0, y: 100.0, vx: 900.0, vy: 1100.0, w: 28.0, h: 44.0, ..State::default() };
+
+        let mut plain = start;
+        let plain_ev = step(&params, &world, &mut plain, Buttons::RIGHT);
+
+        let mut rects = Vec::new();
+        let mut observed = start;
+        let ev = step_with_substeps(&params, &world, &mut observed, Buttons::RIGHT, &mut |r| rects.push(r));
+
+        assert_eq!(format!("{observed:?}"), format!("{plain:?}"));
+        assert_eq!(format!("{ev:?}"), format!("{plain_ev:?}"));
+        assert!(rects.len() > 1, "fast diagonal move should take several substeps");
+        for pair in rects.windows(2) {
+            assert!(pair[1].x >= pair[0].x && pair[1].y >= pair[0].y);
+        }
+        let last = rects.last().unwrap();
+        approx_eq(last.x, plain.x);
+    }
+
+    #[test]
+    fn substep_floors_are_tunable() {
+        let world = [Rect { x: 0.0, y: 480.0, w: 960.0, h: 60.0 }];
+        let falling = State { x: 100.0, y: 100.0, vy: 600.0, w: 28.0, h: 44.0, ..State::default() };
+        let substeps = |params: &Params| {
+            let (mut s, mut n) = (falling, 0);
+            step_with_substeps(params, &world, &mut s, Buttons::empty(), &mut |_| n += 1);
+            (n, s)
+        };
+        let base = Params { world_wrap_mode: 0.0, ..Params::default() };
+        let (n_default, s_default) = substeps(&base);
+
+        // min_substeps: more, smaller substeps (each rounded to whole pixels).
+        let (n, s) = substeps(&Params { min_substeps: 8.0, ..base });
+        assert_eq!((n_default, n), (2, 8));
+        assert!(s.y > falling.y && s.y != s_default.y);
+
+        // min_max_step: a sub-pixel max_step_px is honoured down to the new floor.
+        let tiny = Params { max_step_px: 0.25, ..base };
+        let (n_clamped, _) = substeps(&tiny);
+        let (n_fine, _) = substeps(&Params { min_max_step: 0.5, ..tiny });
+        assert_eq!(n_fine, 2 * n_clamped);
+        assert_eq!(tiny.validate().unwrap_err(), [ParamsWarning::MaxStepBelowMinimum]);
+        assert!(Params { min_max_step: 0.25, ..tiny }.validate().is_ok());
+    }
+
+    #[test]
+    fn max_substeps_bounds_extreme_velocity() {
+        let params = Params {
+            air_max_speed: 1.0e7,
+            terminal_velocity: 1.0e7,
+            max_step_px: 6.0,
+            max_substeps: 8.0,
+            world_wrap_mode: 0.0,
+            ..Params::default()
+        };
+        let mut state = State { x: 0.0, y: 0.0, vx: 1.0e7, vy: 1.0e7, w: 28.0, h: 44.0, ..State::default() };
+
+        let mut substeps = 0;
+        step_with_substeps(&params, &[], &mut state, Buttons::RIGHT, &mut |_| substeps += 1);
+
+        assert!(substeps <= 8, "substeps = {substeps}");
+        assert!(state.x > 0.0 && state.x <= 8.0 * 6.0, "{state:?}");
+        assert!(state.y > 0.0 && state.y <= 8.0 * 6.0, "{state:?}");
+        assert!(state.vx > 0.0 && state.vx < 1.0e7);
+    }
+
+    #[test]
+    fn finer_vertical_step_lands_on_thin_ledge() {
+        let ledge = [Rect { x: 0.0, y: 280.0, w: 200.0, h: 2.0 }];
+        let coarse = Params { world_wrap_mode: 0.0, terminal_velocity: 5000.0, max_step_px: 64.0, ..Params::default() };
+        let start = State { x: 50.0, y: 230.0, vy: 3600.0, w: 28.0, h: 44.0, ..State::default() };
+
+        // One 60px substep carries the whole hitbox past the 2px ledge.
+        let mut tunneled = start;
+        step(&coarse, &ledge, &mut tunneled, Buttons::empty());
+        assert!(tunneled.y > 282.0 && tunneled.grounded == 0, "{tunneled:?}");
+
+        let fine_y = Params { max_step_px_y: 4.0, ..coarse };
+        let mut landed = start;
+        assert_eq!(step(&fine_y, &ledge, &mut landed, Buttons::empty()).landed, 1);
+        assert_eq!((landed.y, landed.grounded), (280.0 - 44.0, 1));
+
+        // Overrides equal to max_step_px are the same as leaving them at 0.
+        let floor = [Rect { x: 0.0, y: 480.0, w: 960.0, h: 60.0 }];
+        let explicit = Params { max_step_px_x: 6.0, max_step_px_y: 6.0, ..Params::default() };
+        let (mut a, mut b) = (start, start);
+        for frame in 0..90 {
+            let buttons = if frame % 20 < 10 { Buttons::RIGHT | Buttons::JUMP } else { Buttons::LEFT };
+            step(&Params::default(), &floor, &mut a, buttons);
+            step(&explicit, &floor, &mut b, buttons);
+        }
+        assert_eq!(format!("{a:?}"), format!("{b:?}"));
+    }
+
+    #[test]
+    fn left_ground_fires_once_walking_off_ledge_and_on_jump() {
+        let params = Params { world_wrap_mode: 0.0, ..Params::default() };
+        let world = [Rect { x: 0.0, y: 480.0, w: 200.0, h: 60.0 }];
+        let mut state = State {
+            x: 150.0,
+            y: 480.0 - 44.0,
+            w: 28.0,
+            h: 44.0,
+            grounded: 1,
+            ..State::default()
+        };
+
+        let mut fired = 0;
+        for _ in 0..60 {
+            let ev = step(&params, &world, &mut state, Buttons::RIGHT);
+            fired += ev.left_ground as u32;
+            assert_eq!(ev.jumped, 0);
+        }
+        assert_eq!(fired, 1);
+        assert_eq!(state.grounded, 0);
+
+        let mut jumper = State { x: 50.0, y: 480.0 - 44.0, w: 28.0, h: 44.0, grounded: 1, ..State::default() };
+        let ev = step(&params, &world, &mut jumper, Buttons::JUMP);
+        assert_eq!((ev.jumped, ev.left_ground), (1, 1));
+        let ev = step(&params, &world, &mut jumper, Buttons::JUMP);
+        assert_eq!(ev.left_ground, 0);
+    }
+
+    #[test]
+    fn growing_under_ledge_pushes_out_instead_of_sticking() {
+        let params = Params { world_wrap_mode: 0.0, ..Params::default() };
+        let world = [
+            Rect { x: 0.0, y: 480.0, w: 960.0, h: 60.0 },
+            // Low ledge: 40px clearance above the floor, too short for a 44px player
+            Rect { x: 0.0, y: 420.0, w: 100.0, h: 20.0 },
+        ];
+        let mut state = State {
+            x: 200.0,
+            y: 480.0 - 44.0,
+            w: 28.0,
+            h: 44.0,
+            grounded: 1,
+            ..State::default()
+        };
+
+        // Duck (feet stay put) and crawl under the ledge.
+        state.h = 24.0;
+        state.y = 480.0 - 24.0;
+        while state.x > 60.0 {
+            step(&params, &world, &mut state, Buttons::LEFT);
+        }
+        for _ in 0..30 {
+            step(&params, &world, &mut state, Buttons::empty());
+        }
+        assert!(state.x < 100.0 && state.grounded != 0, "{state:?}");
+
+        // Stand back up while still under the ledge.
+        state.h = 44.0;
+        state.y = 480.0 - 44.0;
+        step(&params, &world, &mut state, Buttons::empty());
+
+        let r = Rect { x: state.x, y: state.y, w: state.w, h: state.h };
+        assert!(world.iter().all(|p| !rects_intersect(&r, p)), "still embedded: {state:?}");
+        approx_eq(state.x, 100.0);
+        approx_eq(state.y, 480.0 - 44.0);
+        assert_eq!(state.grounded, 1);
+
+        // And it can still walk freely afterwards.
+        for _ in 0..30 {
+            step(&params, &world, &mut state, Buttons::RIGHT);
+        }
+        assert!(state.x > 120.0);
+        approx_eq(state.y, 480.0 - 44.0);
+    }
+
+    #[test]
+    fn run_ramp_accelerates_gradually_into_run_speed() {
+        let world = [Rect { x: 0.0, y: 480.0, w: 100000.0, h: 60.0 }];
+        let start = State { x: 0.0, y: 480.0 - 44.0, w: 28.0, h: 44.0, grounded: 1, ..State::default() };
+        let run_speed = Params::default().ground_max_speed * Params::default().run_multiplier;
+
+        let speed_after = |params: &Params, frames: usize| {
+            let mut s = start;
+            for _ in 0..frames {
+                step(params, &world, &mut s, Buttons::RIGHT | Buttons::RUN);
+            }
+            s.vx
+        };
+
+        let instant = Params { world_wrap_mode: 0.0, ..Params::default() };
+        let ramped = Params { run_ramp_time: 1.0, ..instant };
+
+        // Instant: run speed as soon as acceleration allows (~0.2s).
+        approx_eq(speed_after(&instant, 30), run_speed);
+        // Ramped: still below run speed after half the ramp, but above walk speed.
+        let half = speed_after(&ramped, 30);
+        assert!(half > instant.ground_max_speed && half < run_speed - 1.0, "vx = {half}");
+        approx_eq(speed_after(&ramped, 90), run_speed);
+
+        // Releasing RUN ramps back down to walk speed.
+        let mut s = start;
+        for _ in 0..90 {
+            step(&ramped, &world, &mut s, Buttons::RIGHT | Buttons::RUN);
+        }
+        step(&ramped, &world, &mut s, Buttons::RIGHT);
+        assert!(s.vx > instant.ground_max_speed);
+        assert!(s.run_ramp < 1.0 && s.run_ramp > 0.9);
+    }
+
+    #[test]
+    fn penetration_picks_smallest_axis() {
+        let b = Rect { x: 100.0, y: 100.0, w: 100.0, h: 100.0 };
+
+        // Horizontal: a pokes 5px into b's left side.
+        let a = Rect { x: 75.0, y: 120.0, w: 30.0, h: 30.0 };
+        assert_eq!(penetration(&a, &b), Some((-5.0, 0.0)));
+        // ...and into its right side.
+        let a = Rect { x: 197.0, y: 120.0, w: 30.0, h: 30.0 };
+        assert_eq!(penetration(&a, &b), Some((3.0, 0.0)));
+
+        // Vertical: standing 4px into the top.
+        let a = Rect { x: 120.0, y: 74.0, w: 30.0, h: 30.0 };
+        assert_eq!(penetration(&a, &b), Some((0.0, -4.0)));
+
+        // Corner: 6px deep in X, 2px in Y -> resolve along Y (bottom-right corner).
+        let a = Rect { x: 194.0, y: 198.0, w: 30.0, h: 30.0 };
+        assert_eq!(penetration(&a, &b), Some((0.0, 2.0)));
+        // Corner the other way: 1px in X, 8px in Y -> resolve along X (top-left).
+        let a = Rect { x: 71.0, y: 78.0, w: 30.0, h: 30.0 };
+        assert_eq!(penetration(&a, &b), Some((-1.0, 0.0)));
+
+        // Exactly touching is not overlapping.
+        let a = Rect { x: 200.0, y: 120.0, w: 30.0, h: 30.0 };
+        assert_eq!(penetration(&a, &b), None);
+        let a = Rect { x: 120.0, y: 70.0, w: 30.0, h: 30.0 };
+        assert_eq!(penetration(&a, &b), None);
+
+        // Applying the MTV separates the rects.
+        let a = Rect { x: 194.0, y: 198.0, w: 30.0, h: 30.0 };
+        let (dx, dy) = penetration(&a, &b).unwrap();
+        let moved = Rect { x: a.x + dx, y: a.y + dy, ..a };
+        assert!(!rects_intersect(&moved, &b));
+    }
+
+    #[test]
+    fn smaller_hitbox_lets_sprite_overlap_wall() {
+        let params = Params { world_wrap_mode: 0.0, ..Params::default() };
+        let world = [
+            Rect { x: 0.0, y: 480.0, w: 960.0, h: 60.0 },
+            Rect { x: 200.0, y: 300.0, w: 40.0, h: 180.0 },
+        ];
+        let mut state = State {
+            x: 100.0,
+            y: 480.0 - 48.0,
+            w: 40.0,
+            h: 48.0,
+            grounded: 1,
+            hitbox_offset_x: 8.0,
+            hitbox_offset_y: 4.0,
+            hitbox_w: 24.0,
+            hitbox_h: 44.0,
+            ..State::default()
+        };
+
+        for _ in 0..60 {
+            step(&params, &world, &mut state, Buttons::RIGHT);
+        }
+
+        // Hitbox is flush with the wall; the wider sprite overhangs it by 8px.
+        let hb = state.hitbox();
+        approx_eq(hb.x + hb.w, 200.0);
+        approx_eq(state.x + state.w, 208.0);
+        // Feet of the hitbox rest on the floor; the sprite is drawn 4px higher.
+        approx_eq(hb.y + hb.h, 480.0);
+        approx_eq(state.y, 480.0 - 48.0);
+        assert_eq!(state.grounded, 1);
+
+        // Default hitbox is the full sprite.
+        let plain = State { x: 1.0, y: 2.0, w: 28.0, h: 44.0, ..State::default() };
+        let hb = plain.hitbox();
+        assert_eq!((hb.x, hb.y, hb.w, hb.h), (1.0, 2.0, 28.0, 44.0));
+    }
+
+    #[test]
+    fn disabled_events_do_not_change_physics() {
+        let world = [
+            Rect { x: 0.0, y: 480.0, w: 960.0, h: 60.0 },
+            Rect { x: 0.0, y: 380.0, w: 960.0, h: 20.0 },
+        ];
+        let start = State { x: 100.0, y: 480.0 - 44.0, w: 28.0, h: 44.0, grounded: 1, ..State::default() };
+        let all = Params { world_wrap_mode: 0.0, ..Params::default() };
+        let none = Params { disabled_events: EventMask::all().bits() as f32, ..all };
+
+        let (mut a, mut b) = (start, start);
+        let mut seen = 0u32;
+        for frame in 0..120 {
+            let buttons = match frame % 40 {
+                0 => Buttons::JUMP | Buttons::RIGHT,
+                1..=19 => Buttons::RIGHT | Buttons::DOWN,
+                _ => Buttons::LEFT,
+            };
+            let ea = step(&all, &world, &mut a, buttons);
+            let eb = step(&none, &world, &mut b, buttons);
+            assert_eq!(format!("{a:?}"), format!("{b:?}"), "diverged on frame {frame}");
+            seen += (ea.jumped + ea.bonked + ea.landed + ea.left_ground) as u32;
+            assert_eq!(
+                (eb.jumped, eb.landed, eb.bonked, eb.ground_reason, eb.fast_falling, eb.left_ground),
+                (0, 0, 0, 0, 0, 0)
+            );
+        }
+        // The run exercised the events (including head bonks on the low ceiling).
+        assert!(seen > 0);
+
+        let only_bonk = Params { disabled_events: EventMask::BONKED.bits() as f32, ..all };
+        let mut c = start;
+        let ev = step(&only_bonk, &world, &mut c, Buttons::JUMP);
+        assert_eq!(ev.jumped, 1);
+
+        // Wall indices and typed events are masked too. A low ceiling (bonk with
+        // rebound), a wall (wall_index) and the right world edge (bounce).
+        let bouncy = Params { bonk_restitution: 0.5, world_wrap_mode: 3.0, world_w: 960.0, ..all };
+        let room = [
+            Rect { x: 0.0, y: 480.0, w: 960.0, h: 60.0 },
+            Rect { x: 0.0, y: 420.0, w: 100.0, h: 12.0 },
+            Rect { x: 300.0, y: 300.0, w: 40.0, h: 180.0 },
+        ];
+        let run = |mask: EventMask, x: f32, vx: f32, buttons: Buttons| {
+            let params = Params { disabled_events: mask.bits() as f32, ..bouncy };
+            let mut s = State { x, vx, ..start };
+            let mut out = Vec::new();
+            let ev = step_with_events(&params, &room, &mut s, buttons, &mut out);
+            (ev, out)
+        };
+        let bonk = |mask| run(mask, 40.0, 0.0, Buttons::JUMP);
+        let (ev, out) = bonk(EventMask::empty());
+        assert_eq!(ev.bonk_index, 1);
+        assert!(out.iter().any(|e| matches!(e, SimEvent::BouncedDown { .. })), "{out:?}");
+        let (ev, out) = bonk(EventMask::BONKED);
+        assert_eq!(ev.bonk_index, -1);
+        assert!(!out.iter().any(|e| matches!(e, SimEvent::Bonked { .. } | SimEvent::BouncedDown { .. })), "{out:?}");
+
+        let wall = |mask| run(mask, 271.0, 260.0, Buttons::RIGHT).0;
+        assert_eq!(wall(EventMask::empty()).wall_index, 2);
+        assert_eq!(wall(EventMask::WALL).wall_index, -1);
+        assert!(wall(EventMask::empty()).mask().contains(EventMask::WALL));
+
+        let edge = |mask| run(mask, 930.0, 220.0, Buttons::RIGHT).1;
+        assert!(edge(EventMask::empty()).iter().any(|e| matches!(e, SimEvent::Bounced { .. })));
+        assert!(!edge(EventMask::BOUNCED).iter().any(|e| matches!(e, SimEvent::Bounced { .. })));
+    }
+
+    #[test]
+    fn brush_contact_does_not_rearm_coyote() {
+        let params = Params { world_wrap_mode: 0.0, ..Params::default() };
+        let world = [Rect { x: 0.0, y: 400.0, w: 100.0, h: 20.0 }];
+
+        // Drop onto the ledge at `x` while running right and keep running off its
+        // lip; returns how many frames the player was grounded and whether a jump
+        // two frames after walking off (inside coyote_time) fires.
+        let drop_and_run_off = |x: f32| {
+            let mut s = State { x, y: 400.0 - 44.0 - 4.0, vx: 260.0, vy: 240.0, w: 28.0, h: 44.0, ..State::default() };
+            assert_eq!(step(&params, &world, &mut s, Buttons::RIGHT).landed, 1, "should land: {s:?}");
+            let mut grounded_frames = 1;
+            while step(&params, &world, &mut s, Buttons::RIGHT).left_ground == 0 {
+                grounded_frames += 1;
+                assert!(grounded_frames < 10, "never left the ledge: {s:?}");
+            }
+            step(&params, &world, &mut s, Buttons::RIGHT);
+            (grounded_frames, step(&params, &world, &mut s, Buttons::RIGHT | Buttons::JUMP).jumped)
+        };
+
+        // Brushing the lip for a single frame grants no fresh coyote window...
+        assert_eq!(drop_and_run_off(93.0), (1, 0));
+        // ...while standing on the ledge a full frame before running off does.
+        assert_eq!(drop_and_run_off(89.0), (2, 1));
+    }
+
+    #[test]
+    fn metrics_track_airtime_fall_speed_and_distance() {
+        let params = Params { world_wrap_mode: 0.0, ..Params::default() };
+        let world = [Rect { x: 0.0, y: 480.0, w: 960.0, h: 60.0 }];
+        let mut state = State { x: 100.0, y: 480.0 - 44.0, w: 28.0, h: 44.0, grounded: 1, ..State::default() };
+
+        let mut air = 0u32;
+        let mut peak_vy = 0.0f32;
+        let mut landed = false;
+        for _ in 0..120 {
+            let ev = step(&params, &world, &mut state, Buttons::JUMP | Buttons::RIGHT);
+            peak_vy = peak_vy.max(state.vy).max(ev.vy_pre_collision);
+            if ev.landed != 0 {
+                landed = true;
+                break;
+            }
+            air += 1;
+            assert_eq!(state.airtime_frames, air);
+        }
+        assert!(landed);
+        assert_eq!(state.airtime_frames, 0);
+        assert_eq!(state.max_airtime_frames, air);
+        assert!(air > 20);
+        approx_eq(state.max_fall_speed, peak_vy);
+        assert!(state.max_fall_speed > 0.0);
+        approx_eq(state.distance_x, state.x - 100.0);
+
+        state.reset_metrics();
+        assert_eq!((state.max_airtime_frames, state.max_fall_speed, state.distance_x), (0, 0.0, 0.0));
+    }
+
+    #[test]
+    fn max_fall_speed_includes_impact_frame() {
+        let params = Params { world_wrap_mode: 0.0, ..Params::default() };
+        let world = [Rect { x: 0.0, y: 480.0, w: 960.0, h: 60.0 }];
+        let mut state = State { x: 100.0, y: 200.0, w: 28.0, h: 44.0, ..State::default() };
+
+        let mut airborne_peak = 0.0f32;
+        let impact_vy = loop {
+            let ev = step(&params, &world, &mut state, Buttons::empty());
+            if ev.landed != 0 {
+                break ev.vy_pre_collision;
+            }
+            airborne_peak = airborne_peak.max(state.vy);
+        };
+        assert_eq!(state.vy, 0.0);
+        assert!(impact_vy > airborne_peak);
+        assert_eq!(state.max_fall_speed, impact_vy);
+    }
+
+    #[test]
+    fn explicit_integration_trails_semi_implicit_by_documented_offset() {
+        // gravity * DT^2 = 1px and one substep per frame (no per-substep rounding),
+        // so the offset after n frames is exactly n px.
+        let semi = Params {
+            gravity_down: 3600.0,
+            terminal_velocity: 5000.0,
+            snap_to_ground: 0.0,
+            max_step_px: 100.0,
+            world_wrap_mode: 0.0,
+            ..Params::default()
+        };
+        let explicit = Params { integration_mode: 1.0, ..semi };
+        let start = State { x: 100.0, y: 0.0, w: 28.0, h: 44.0, ..State::default() };
+
+        let (mut a, mut b) = (start, start);
+        for n in 1..=20 {
+            step(&semi, &[], &mut a, Buttons::empty());
+            step(&explicit, &[], &mut b, Buttons::empty());
+            approx_eq(a.y, (n * (n + 1) / 2) as f32);
+            approx_eq(b.y, (n * (n - 1) / 2) as f32);
+            approx_eq(a.y - b.y, n as f32);
+            approx_eq(a.vy, b.vy);
+        }
+    }
+
+    #[test]
+    fn degenerate_rects_never_collide() {
+        let params = Params { world_wrap_mode: 0.0, ..Params::default() };
+        let world = [
+            Rect { x: 0.0, y: 300.0, w: 960.0, h: 0.0 },
+            Rect { x: 400.0, y: 0.0, w: 0.0, h: 540.0 },
+            Rect { x: 0.0, y: 480.0, w: 960.0, h: 60.0 },
+        ];
+
+        // A zero-height "floor" neither catches nor snaps the player.
+        let mut state = State { x: 100.0, y: 200.0, w: 28.0, h: 44.0, ..State::default() };
+        for _ in 0..90 {
+            step(&params, &world, &mut state, Buttons::RIGHT);
+        }
+        approx_eq(state.y, 480.0 - 44.0);
+        assert_eq!(state.grounded, 1);
+        // ...and the zero-width wall at x = 400 doesn't block.
+        for _ in 0..90 {
+            step(&params, &world, &mut state, Buttons::RIGHT);
+        }
+        assert!(state.x > 400.0);
+
+        // A zero-size player doesn't panic or get stuck; it just collides with nothing.
+        let mut dot = State { x: 100.0, y: 470.0, ..State::default() };
+        for frame in 0..60 {
+            let buttons = if frame % 10 == 0 { Buttons::JUMP | Buttons::LEFT } else { Buttons::LEFT };
+            step(&params, &world, &mut dot, buttons);
+            assert!(dot.x.is_finite() && dot.y.is_finite());
+        }
+        assert_eq!(dot.grounded, 0);
+        assert!(dot.y > 480.0);
+    }
+
+    #[test]
+    fn jump_apex_height_matches_analytic_value() {
+        let params = Params { world_wrap_mode: 0.0, ..Params::default() };
+        let world = [Rect { x: 0.0, y: 480.0, w: 960.0, h: 60.0 }];
+        let mut state = State { x: 100.0, y: 480.0 - 44.0, w: 28.0, h: 44.0, grounded: 1, ..State::default() };
+
+        let mut min_y = state.y;
+        let mut landed = false;
+        for _ in 0..120 {
+            let ev = step(&params, &world, &mut state, Buttons::JUMP);
+            min_y = min_y.min(state.y);
+            if ev.landed != 0 {
+                landed = true;
+                break;
+            }
+        }
+        assert!(landed);
+        assert_eq!(state.jump_active, 0);
+        approx_eq(state.last_jump_apex_height, 480.0 - 44.0 - min_y);
+
+        // v^2 / 2g, within one frame of rise at launch speed (discrete integration).
+        let analytic = params.jump_velocity * params.jump_velocity / (2.0 * params.gravity_up);
+        let tol = params.jump_velocity * DT;
+        assert!(
+            (state.last_jump_apex_height - analytic).abs() <= tol,
+            "apex {} vs analytic {analytic}",
+            state.last_jump_apex_height
+        );
+
+        // Kept after landing, reset by the next jump.
+        let before = state.last_jump_apex_height;
+        step(&params, &world, &mut state, Buttons::empty());
+        approx_eq(state.last_jump_apex_height, before);
+        step(&params, &world, &mut state, Buttons::JUMP);
+        assert!(state.last_jump_apex_height < before);
+    }
+
+    #[test]
+    fn respawn_mid_jump_gives_clean_standing_state() {
+        let params = Params { run_ramp_time: 0.5, world_wrap_mode: 0.0, ..Params::default() };
+        let world = [Rect { x: 0.0, y: 480.0, w: 960.0, h: 60.0 }];
+        let mut state = State {
+            x: 100.0,
+            y: 480.0 - 44.0,
+            w: 28.0,
+            h: 44.0,
+            grounded: 1,
+            hitbox_w: 24.0,
+            ..State::default()
+        };
+        for _ in 0..8 {
+            step(&params, &world, &mut state, Buttons::JUMP | Buttons::RIGHT | Buttons::RUN);
+        }
+        assert!(state.grounded == 0 && state.vy < 0.0 && state.jump_active == 1);
+
+        respawn(&mut state, 300.0, 480.0 - 44.0);
+        assert_eq!((state.x, state.y, state.w, state.h, state.hitbox_w), (300.0, 436.0, 28.0, 44.0, 24.0));
+        assert_eq!((state.vx, state.vy, state.coyote, state.jump_buffer, state.run_ramp), (0.0, 0.0, 0.0, 0.0, 0.0));
+        assert_eq!((state.jump_was_down, state.snap_suppress, state.jump_active, state.airtime_frames), (0, 0, 0, 0));
+
+        let ev = step(&params, &world, &mut state, Buttons::empty());
+        assert_eq!(ev.jumped, 0);
+        assert_eq!(state.grounded, 1);
+        approx_eq(state.y, 480.0 - 44.0);
+        approx_eq(state.x, 300.0);
+        approx_eq(state.vy, 0.0);
+    }
+
+    #[test]
+    fn pushing_reports_direction_only_when_blocked() {
+        let params = Params { world_wrap_mode: 0.0, ..Params::default() };
+        let world = [
+            Rect { x: 0.0, y: 480.0, w: 960.0, h: 60.0 },
+            Rect { x: 200.0, y: 380.0, w: 40.0, h: 100.0 },
+        ];
+        let mut state = State { x: 100.0, y: 480.0 - 44.0, w: 28.0, h: 44.0, grounded: 1, ..State::default() };
+
+        // Walking freely toward the wall.
+        step(&params, &world, &mut state, Buttons::RIGHT);
+        assert_eq!(state.pushing, 0);
+
+        for _ in 0..60 {
+            step(&params, &world, &mut state, Buttons::RIGHT);
+        }
+        approx_eq(state.x, 200.0 - 28.0);
+        assert_eq!(state.pushing, 1);
+
+        // Releasing input or walking away clears it.
+        step(&params, &world, &mut state, Buttons::empty());
+        assert_eq!(state.pushing, 0);
+        step(&params, &world, &mut state, Buttons::LEFT);
+        assert_eq!(state.pushing, 0);
+
+        // Holding toward open space while touching the wall is not pushing.
+        let mut touching = State { x: 240.0, ..state };
+        step(&params, &world, &mut touching, Buttons::RIGHT);
+        assert_eq!(touching.pushing, 0);
+        let mut against = State { x: 240.0, vx: 0.0, ..state };
+        step(&params, &world, &mut against, Buttons::LEFT);
+        assert_eq!(against.pushing, -1);
+    }
+
+    #[test]
+    fn step_profiled_counts_brute_force_intersect_tests() {
+        let params = Params { world_wrap_mode: 0.0, snap_to_ground: 0.0, ..Params::default() };
+        // Geometry far away from the player: every test misses.
+        let world: Vec<Rect> = (0..25)
+            .map(|i| Rect { x: 2000.0 + 40.0 * i as f32, y: 2000.0, w: 32.0, h: 16.0 })
+            .collect();
+        let mut state = State { x: 100.0, y: 100.0, vx: 900.0, vy: 900.0, w: 28.0, h: 44.0, ..State::default() };
+
+        let mut stats = StepStats::default();
+        step_profiled(&params, &world, &mut state, Buttons::RIGHT, &mut stats);
+        assert!(stats.substeps > 1);
+        assert_eq!(stats.intersect_tests, 2 * world.len() as u32 * stats.substeps);
+        assert_eq!(stats.corrections, 0);
+
+        // Counters accumulate, and a wall contact shows up as a correction.
+        let wall = [Rect { x: state.x + state.w + 1.0, y: 0.0, w: 40.0, h: 540.0 }];
+        let before = stats;
+        step_profiled(&params, &wall, &mut state, Buttons::RIGHT, &mut stats);
+        assert!(stats.substeps > before.substeps);
+        assert!(stats.corrections > 0);
+    }
+
+    #[test]
+    fn ground_normal_follows_slope_and_flat_ground() {
+        let params = Params { world_wrap_mode: 0.0, ..Params::default() };
+        let world = [Rect { x: 0.0, y: 480.0, w: 960.0, h: 60.0 }];
+        // 45 degrees, descending to the right; long enough to walk along.
+        let slope = ObbRect { cx: 300.0, cy: 300.0, hw: 200.0, hh: 20.0, angle: 45f32.to_radians() };
+        let mut state = State { x: 230.0, y: 150.0, w: 28.0, h: 44.0, ..State::default() };
+
+        let mut on_slope = 0;
+        for _ in 0..40 {
+            step_with_obbs(&params, &world, &[slope], &mut state, Buttons::RIGHT);
+            if state.grounded != 0 {
+                on_slope += 1;
+                let expected = 0.5f32.sqrt();
+                assert!((state.ground_normal_x - expected).abs() < 0.05, "{state:?}");
+                assert!((state.ground_normal_y + expected).abs() < 0.05, "{state:?}");
+            } else {
+                assert_eq!((state.ground_normal_x, state.ground_normal_y), (0.0, 0.0));
+            }
+        }
+        assert!(on_slope > 5);
+
+        let mut flat = State { x: 700.0, y: 480.0 - 44.0, w: 28.0, h: 44.0, grounded: 1, ..State::default() };
+        step(&params, &world, &mut flat, Buttons::RIGHT);
+        assert_eq!((flat.ground_normal_x, flat.ground_normal_y), (0.0, -1.0));
+        step(&params, &world, &mut flat, Buttons::JUMP);
+        assert_eq!((flat.ground_normal_x, flat.ground_normal_y), (0.0, 0.0));
+    }
+
+    #[test]
+    fn external_velocity_clears_a_wall_a_jump_cannot() {
+        let params = Params { world_wrap_mode: 0.0, ..Params::default() };
+        let world = [
+            Rect { x: 0.0, y: 480.0, w: 960.0, h: 60.0 },
+            Rect { x: 300.0, y: 480.0 - 130.0, w: 40.0, h: 130.0 },
+        ];
+        let start = State { x: 200.0, y: 480.0 - 44.0, w: 28.0, h: 44.0, grounded: 1, ..State::default() };
+
+        let run = |gust: f32| {
+            let mut s = start;
+            for frame in 0..90 {
+                let ext_vy = if frame == 0 { gust } else { 0.0 };
+                step_ext(&params, &world, &mut s, Buttons::JUMP | Buttons::RIGHT, 0.0, ext_vy);
+            }
+            s
+        };
+
+        let plain = run(0.0);
+        assert!(plain.x + plain.w <= 300.0, "plain jump should be blocked: {plain:?}");
+
+        let boosted = run(-300.0);
+        assert!(boosted.x > 340.0, "boosted jump should clear the wall: {boosted:?}");
+
+        // A zero shove is exactly `step`.
+        let mut a = start;
+        let mut b = start;
+        step(&params, &world, &mut a, Buttons::RIGHT);
+        step_ext(&params, &world, &mut b, Buttons::RIGHT, 0.0, 0.0);
+        assert_eq!(format!("{a:?}"), format!("{b:?}"));
+    }
+
+    #[test]
+    fn upward_speed_clamped_to_max_rise_speed() {
+        let world: [Rect; 0] = [];
+        let launch = |max_rise_speed: f32| {
+            let params = Params { max_rise_speed, ..Params::default() };
+            let mut s = State { x: 0.0, y: 0.0, w: 10.0, h: 10.0, vy: -9000.0, ..State::default() };
+            step(&params, &world, &mut s, Buttons::empty());
+            s.vy
+        };
+
+        assert_eq!(launch(MAX_UPWARD_SPEED), -5000.0);
+        assert_eq!(launch(8000.0), -8000.0);
+        assert!(launch(10000.0) > -9000.0 && launch(10000.0) < -8000.0);
+    }
+
+    #[test]
+    fn fell_too_far_fires_once_past_max_fall_distance() {
+        let floor = [Rect { x: 0.0, y: 480.0, w: 200.0, h: 20.0 }];
+        let params = Params { world_wrap_mode: 0.0, max_fall_distance: 300.0, ..Params::default() };
+        let mut s = State { x: 40.0, y: 480.0 - 44.0, w: 28.0, h: 44.0, grounded: 1, ..State::default() };
+
+        // Walk off the right edge into the void.
+        let mut fired_at = None;
+        for frame in 0..120 {
+            let ev = step(&params, &floor, &mut s, Buttons::RIGHT);
+            if ev.fell_too_far != 0 {
+                assert!(fired_at.is_none(), "fired twice");
+                fired_at = Some((frame, s.y));
+            }
+        }
+        let (_, y) = fired_at.expect("never fired");
+        assert!(y >= 480.0 - 44.0 + 300.0);
+        assert!(s.fall_distance > 300.0);
+
+        // Landing resets the distance; a short hop never reaches the threshold.
+        let mut s = State { x: 40.0, y: 480.0 - 44.0, w: 28.0, h: 44.0, grounded: 1, ..State::default() };
+        for frame in 0..90 {
+            let buttons = if frame == 0 { Buttons::JUMP } else { Buttons::empty() };
+            assert_eq!(step(&params, &floor, &mut s, buttons).fell_too_far, 0);
+        }
+        assert_eq!((s.grounded, s.fall_distance), (1, 0.0));
+    }
+
+    #[test]
+    fn swept_aabb_encloses_start_and_end() {
+        let r = Rect { x: 10.0, y: 20.0, w: 8.0, h: 16.0 };
+        let check = |dx: f32, dy: f32, expected: (f32, f32, f32, f32)| {
+            let s = swept_aabb(&r, dx, dy);
+            assert_eq!((s.x, s.y, s.w, s.h), expected, "dx={dx} dy={dy}");
+        };
+
+        check(5.0, 0.0, (10.0, 20.0, 13.0, 16.0));
+        check(-5.0, 0.0, (5.0, 20.0, 13.0, 16.0));
+        check(-4.0, 6.0, (6.0, 20.0, 12.0, 22.0));
+        check(3.0, -7.0, (10.0, 13.0, 11.0, 23.0));
+        check(0.0, 0.0, (10.0, 20.0, 8.0, 16.0));
+    }
+
+    #[test]
+    fn probe_move_matches_resolver_without_state() {
+        let world = [
+            Rect { x: 0.0, y: 100.0, w: 200.0, h: 20.0 },
+            Rect { x: 120.0, y: 40.0, w: 20.0, h: 60.0 },
+            Rect { x: 0.0, y: 0.0, w: 200.0, h: 10.0 },
+        ];
+        let r = Rect { x: 80.0, y: 60.0, w: 20.0, h: 40.0 };
+
+        // Open space: moves the full distance.
+        let (open, ground, head) = probe_move(r, -30.0, -20.0, &world);
+        assert_eq!((open.x, open.y, ground, head), (50.0, 40.0, false, false));
+
+        // Into the wall: stopped flush against its left face.
+        let (wall, ground, head) = probe_move(r, 50.0, 0.0, &world);
+        assert_eq!((wall.x, wall.y, ground, head), (100.0, 60.0, false, false));
+
+        // Down onto the floor and up into the ceiling.
+        let (floor, ground, _) = probe_move(Rect { y: 50.0, ..r }, 0.0, 30.0, &world);
+        assert_eq!((floor.y, ground), (60.0, true));
+        let (ceiling, _, head) = probe_move(r, 0.0, -80.0, &world);
+        assert_eq!((ceiling.y, head), (10.0, true));
+    }
+
+    #[test]
+    fn jump_wasted_fires_when_air_buffer_expires() {
+        let floor = [Rect { x: 0.0, y: 480.0, w: 400.0, h: 20.0 }];
+        let params = Params { world_wrap_mode: 0.0, ..Params::default() };
+        let drop = |press_frames: &[u32]| {
+            let mut s = State { x: 100.0, y: 100.0, w: 28.0, h: 44.0, ..State::default() };
+            let mut wasted = Vec::new();
+            let mut jumps = 0;
+            for frame in 0..120 {
+                let buttons = if press_frames.contains(&frame) { Buttons::JUMP } else { Buttons::empty() };
+                let ev = step(&params, &floor, &mut s, buttons);
+                if ev.jump_wasted != 0 {
+                    wasted.push(frame);
+                }
+                jumps += ev.jumped as u32;
+            }
+            (wasted, jumps)
+        };
+
+        // Mashing high in the air: each press's buffer runs out unused.
+        let (wasted, jumps) = drop(&[2, 4, 20]);
+        assert_eq!(jumps, 0);
+        assert_eq!(wasted.len(), 2);
+        assert!(wasted[0] > 4 && wasted[1] > 20);
+
+        // A press just before landing is consumed on touchdown: never wasted.
+        let mut s = State { x: 100.0, y: 100.0, w: 28.0, h: 44.0, ..State::default() };
+        let land = (0..120).find(|_| step(&params, &floor, &mut s, Buttons::empty()).landed != 0).unwrap();
+        let (wasted, jumps) = drop(&[land - 2]);
+        assert_eq!((wasted.len(), jumps), (0, 1));
+    }
+
+    #[test]
+    fn rects_in_view_culls_and_wraps_across_seam() {
+        let world = [
+            Rect { x: 0.0, y: 400.0, w: 100.0, h: 20.0 },
+            Rect { x: 180.0, y: 300.0, w: 60.0, h: 20.0 },
+            Rect { x: 500.0, y: 300.0, w: 40.0, h: 20.0 },
+            Rect { x: 900.0, y: 400.0, w: 60.0, h: 20.0 },
+            Rect { x: 200.0, y: 900.0, w: 60.0, h: 20.0 },
+        ];
+        let mut out = Vec::new();
+
+        // Partially visible rects count; fully off-screen ones (right, below) don't.
+        let view = Rect { x: 50.0, y: 0.0, w: 160.0, h: 480.0 };
+        rects_in_view(&view, &world, &mut out);
+        assert_eq!(out, [0, 1]);
+
+        let off = Rect { x: 600.0, y: 0.0, w: 200.0, h: 200.0 };
+        rects_in_view(&off, &world, &mut out);
+        assert!(out.is_empty());
+
+        // A view hanging off the right edge sees the left-edge rect through the seam.
+        let seam = Rect { x: 880.0, y: 0.0, w: 160.0, h: 480.0 };
+        let wrapping = Params { world_w: 960.0, world_wrap_mode: 2.0, ..Params::default() };
+        rects_in_view_wrapped(&wrapping, &seam, &world, &mut out);
+        assert_eq!(out, [0, 3]);
+        let walled = Params { world_wrap_mode: 0.0, ..wrapping };
+        rects_in_view_wrapped(&walled, &seam, &world, &mut out);
+        assert_eq!(out, [3]);
+    }
+
+    #[test]
+    fn coyote_frames_grants_exact_frame_count() {
+        let ledge = [Rect { x: 0.0, y: 480.0, w: 100.0, h: 20.0 }];
+        let params = Params { world_wrap_mode: 0.0, coyote_frames: 5.0, ..Params::default() };
+        let start = State { x: 60.0, y: 480.0 - 44.0, w: 28.0, h: 44.0, grounded: 1, ..State::default() };
+
+        // Walk right until the first airborne frame.
+        let mut off = start;
+        while step(&params, &ledge, &mut off, Buttons::RIGHT).left_ground == 0 {}
+
+        // Jumping on airborne frame k (1-based) works for k <= 5 only.
+        let jumps_on = |k: u32| {
+            let mut s = off;
+            for _ in 1..k {
+                step(&params, &ledge, &mut s, Buttons::RIGHT);
+            }
+            step(&params, &ledge, &mut s, Buttons::RIGHT | Buttons::JUMP).jumped != 0
+        };
+        let grace: Vec<bool> = (1..=7).map(jumps_on).collect();
+        assert_eq!(grace, [true, true, true, true, true, false, false]);
+    }
+
+    #[test]
+    fn grounded_grace_allows_jump_after_ground_drops_away() {
+        let none: [Rect; 0] = [];
+        let params = Params { world_wrap_mode: 0.0, coyote_time: 0.0, grounded_grace_frames: 3.0, ..Params::default() };
+        // Touched down last frame, so coyote wouldn't re-arm either way.
+        let start = State { x: 100.0, y: 480.0 - 44.0, w: 28.0, h: 44.0, grounded: 1, just_landed: 1, ..State::default() };
+
+        // The floor vanishes: the first step leaves the ground.
+        let mut dropped = start;
+        assert_eq!(step(&params, &none, &mut dropped, Buttons::empty()).left_ground, 1);
+        assert_eq!((dropped.coyote, dropped.grounded_recently), (0.0, 3));
+
+        let jumps_on = |params: &Params, k: u32| {
+            let mut s = dropped;
+            for _ in 1..k {
+                step(params, &none, &mut s, Buttons::empty());
+            }
+            step(params, &none, &mut s, Buttons::JUMP).jumped != 0
+        };
+        let grace: Vec<bool> = (1..=5).map(|k| jumps_on(&params, k)).collect();
+        assert_eq!(grace, [true, true, true, false, false]);
+
+        let off = Params { grounded_grace_frames: 0.0, ..params };
+        let mut s = start;
+        step(&off, &none, &mut s, Buttons::empty());
+        assert_eq!(step(&off, &none, &mut s, Buttons::JUMP).jumped, 0);
+    }
+
+    #[test]
+    fn auto_bounce_rejumps_while_jump_held() {
+        let floor = [Rect { x: 0.0, y: 480.0, w: 960.0, h: 60.0 }];
+        let start = State { x: 100.0, y: 480.0 - 44.0, w: 28.0, h: 44.0, grounded: 1, ..State::default() };
+        let hold_jump = |params: &Params| {
+            let mut s = start;
+            let mut jumps = Vec::new();
+            for frame in 0..300 {
+                if step(params, &floor, &mut s, Buttons::JUMP).jumped != 0 {
+                    jumps.push(frame);
+                }
+            }
+            jumps
+        };
+
+        let params = Params { world_wrap_mode: 0.0, ..Params::default() };
+        assert_eq!(hold_jump(&params), [0]);
+
+        let bouncy = Params { auto_bounce: 1.0, ..params };
+        let jumps = hold_jump(&bouncy);
+        assert!(jumps.len() > 3, "{jumps:?}");
+        // Evenly spaced: every bounce goes off on the frame after touchdown.
+        let gaps: Vec<i32> = jumps.windows(2).map(|w| w[1] - w[0]).collect();
+        assert!(gaps.windows(2).all(|g| g[0] == g[1]), "{gaps:?}");
+
+        // Releasing in the air lands normally.
+        let mut s = start;
+        step(&bouncy, &floor, &mut s, Buttons::JUMP);
+        let landed = (0..120).any(|_| step(&bouncy, &floor, &mut s, Buttons::empty()).landed != 0);
+        assert!(landed);
+        assert_eq!(step(&bouncy, &floor, &mut s, Buttons::empty()).jumped, 0);
+    }
+
+    #[test]
+    fn abi_v1_fields_keep_their_offsets() {
+        use std::mem::offset_of;
+        // Appended fields must never shift the v1 layouts hosts were built against.
+        assert_eq!(offset_of!(Params, ground_max_speed), 0);
+        assert_eq!(offset_of!(Params, air_max_speed), 5 * 4);
+        assert_eq!(offset_of!(Params, gravity_up), 9 * 4);
+        assert_eq!(offset_of!(Params, jump_velocity), 13 * 4);
+        assert_eq!(offset_of!(Params, snap_to_ground), 17 * 4);
+        assert_eq!(offset_of!(Params, world_wrap_mode), 20 * 4);
+        assert_eq!((offset_of!(State, grounded), offset_of!(State, jump_was_down)), (24, 36));
+        assert_eq!((offset_of!(Events, landed), offset_of!(Events, bonked)), (1, 2));
+    }
+
+    #[test]
+    fn jump_cut_gravity_boost_speeds_up_cut_hops() {
+        let floor = [Rect { x: 0.0, y: 480.0, w: 960.0, h: 60.0 }];
+        let standing = State { x: 100.0, y: 480.0 - 44.0, w: 28.0, h: 44.0, grounded: 1, ..State::default() };
+        // Change in vy over the frame after frame 1 releases (cuts) or keeps holding JUMP.
+        let dv_after = |params: &Params, cut: bool| {
+            let mut s = standing;
+            let held = if cut { Buttons::empty() } else { Buttons::JUMP };
+            step(params, &floor, &mut s, Buttons::JUMP);
+            step(params, &floor, &mut s, held);
+            let before = s.vy;
+            step(params, &floor, &mut s, held);
+            (s.vy - before, s)
+        };
+
+        let params = Params { world_wrap_mode: 0.0, ..Params::default() };
+        let boosted = Params { jump_cut_gravity_boost: 2.0, jump_cut_boost_frames: 12.0, ..params };
+        let (uncut, _) = dv_after(&boosted, false);
+        let (cut, s) = dv_after(&boosted, true);
+        approx_eq(uncut, params.gravity_up * DT);
+        approx_eq(cut, 2.0 * uncut);
+        assert_eq!(s.jump_cut_timer, 11);
+        // The default boost of 1 leaves cut hops alone.
+        approx_eq(dv_after(&params, true).0, uncut);
+
+        // A cut hop lands before the boost runs out, and landing clears it.
+        let mut s = standing;
+        step(&boosted, &floor, &mut s, Buttons::JUMP);
+        let frames = (0..120).take_while(|_| step(&boosted, &floor, &mut s, Buttons::empty()).landed == 0).count();
+        assert!(frames < 12);
+        assert_eq!(s.jump_cut_timer, 0);
+    }
+
+    #[test]
+    fn jump_buffer_frames_counts_press_frame() {
+        let floor = [Rect { x: 0.0, y: 480.0, w: 400.0, h: 20.0 }];
+        let params = Params { world_wrap_mode: 0.0, jump_buffer_frames: 3.0, ..Params::default() };
+        let s = State { x: 100.0, y: 300.0, w: 28.0, h: 44.0, ..State::default() };
+        let land = {
+            let mut probe = s;
+            (0..120).find(|_| step(&params, &floor, &mut probe, Buttons::empty()).landed != 0).unwrap()
+        };
+
+        // The jump fires on the first grounded frame (land + 1). Three frames of
+        // buffer cover press, press + 1 and press + 2.
+        let run = |press: u32| {
+            let mut s = s;
+            (0..=land + 1).any(|f| {
+                let b = if f == press { Buttons::JUMP } else { Buttons::empty() };
+                step(&params, &floor, &mut s, b).jumped != 0
+            })
+        };
+        assert!(run(land - 1));
+        assert!(!run(land - 2));
+    }
+
+    #[test]
+    fn step_pure_idle_grounded_player_stays_put() {
+        let params = Params { world_wrap_mode: 0.0, ..Params::default() };
+        let mut rng = Rng::new(401);
+        for _ in 0..200 {
+            let floor_y = rng.range_u32(100, 900) as f32;
+            let w = rng.range_u32(8, 48) as f32;
+            let h = rng.range_u32(8, 64) as f32;
+            let x = rng.range_u32(0, 800) as f32;
+            let world = [Rect { x: -100.0, y: floor_y, w: 1200.0, h: 40.0 }];
+            let mut state = State { x, y: floor_y - h, w, h, grounded: 1, ..State::default() };
+
+            for _ in 0..rng.range_u32(1, 30) {
+                let (next, ev) = step_pure(&params, &world, state, Buttons::empty());
+                assert_eq!((next.x, next.y, next.vy, next.grounded), (x, floor_y - h, 0.0, 1), "{state:?}");
+                assert_eq!((ev.jumped, ev.left_ground), (0, 0));
+                state = next;
+            }
+        }
+    }
+
+    #[test]
+    fn property_random_worlds_never_end_frame_overlapping() {
+        let bounds = Rect { x: 0.0, y: 0.0, w: 960.0, h: 540.0 };
+        let mut rng = Rng::new(402);
+        for case in 0..64u64 {
+            let mut world = random_world(case, rng.range_u32(5, 40) as usize, bounds);
+            world.push(Rect { x: -200.0, y: 540.0, w: 1360.0, h: 40.0 });
+            let params = Params {
+                world_wrap_mode: 0.0,
+                ground_max_speed: rng.range_u32(100, 600) as f32,
+                jump_velocity: rng.range_u32(300, 1200) as f32,
+                gravity_down: rng.range_u32(800, 4000) as f32,
+                terminal_velocity: rng.range_u32(400, 3000) as f32,
+                max_step_px: rng.range_u32(1, 12) as f32,
+                snap_to_ground: rng.range_u32(0, 8) as f32,
+                ..Params::default()
+            };
+
+            let (w, h) = (rng.range_u32(8, 32) as f32, rng.range_u32(12, 48) as f32);
+            let mut s = State { w, h, ..State::default() };
+            loop {
+                s.x = rng.range_u32(0, 900) as f32;
+                s.y = rng.range_u32(0, 480) as f32;
+                if !world.iter().any(|p| rects_intersect(&s.hitbox(), p)) {
+                    break;
+                }
+            }
+
+            for frame in 0..300 {
+                let buttons = Buttons::from_bits_truncate(rng.next_u64() as u8);
+                step(&params, &world, &mut s, buttons);
+
+                let ctx = format!("case {case} frame {frame} {s:?}");
+                assert!(s.x.is_finite() && s.y.is_finite() && s.vx.is_finite() && s.vy.is_finite(), "{ctx}");
+                assert!(s.vy >= -params.max_rise_speed && s.vy <= params.terminal_velocity, "{ctx}");
+                let hitbox = s.hitbox();
+                assert!(!world.iter().any(|p| rects_intersect(&hitbox, p)), "{ctx}");
+            }
+        }
+    }
+
+    #[test]
+    fn land_toi_reports_when_in_frame_ground_was_hit() {
+        let floor = [Rect { x: 0.0, y: 480.0, w: 400.0, h: 20.0 }];
+        let params = Params { world_wrap_mode: 0.0, max_step_px: 1.0, ..Params::default() };
+        // Falling ~20px this frame from `gap` px above the floor.
+        let land = |gap: f32| {
+            let mut s = State { x: 100.0, y: 480.0 - 44.0 - gap, w: 28.0, h: 44.0, vy: 1180.0, ..State::default() };
+            step(&params, &floor, &mut s, Buttons::empty())
+        };
+
+        let early = land(2.0);
+        assert_eq!(early.landed, 1);
+        assert!(early.land_toi > 0.0 && early.land_toi <= 0.15, "{}", early.land_toi);
+
+        let late = land(18.0);
+        assert_eq!(late.landed, 1);
+        assert!(late.land_toi > 0.85 && late.land_toi <= 1.0, "{}", late.land_toi);
+
+        // No landing: stays 0.
+        assert_eq!(land(200.0).land_toi, 0.0);
+    }
+
+    #[test]
+    fn freeze_axes_pins_x_while_gravity_still_applies() {
+        let world = [Rect { x: 0.0, y: 480.0, w: 400.0, h: 20.0 }];
+        let params = Params { world_wrap_mode: 0.0, freeze_axes: 1.0, ..Params::default() };
+        let mut s = State { x: 100.0, y: 300.0, w: 28.0, h: 44.0, vx: 250.0, ..State::default() };
+
+        let mut landed = false;
+        for _ in 0..90 {
+            landed |= step(&params, &world, &mut s, Buttons::RIGHT | Buttons::RUN).landed != 0;
+            assert_eq!((s.x, s.vx), (100.0, 0.0));
+        }
+        assert!(landed);
+        assert_eq!((s.y, s.grounded), (436.0, 1));
+
+        // Freezing y instead holds the player mid-air but lets them walk.
+        let params = Params { freeze_axes: 2.0, ..params };
+        let mut s = State { x: 100.0, y: 300.0, w: 28.0, h: 44.0, ..State::default() };
+        for _ in 0..30 {
+            step(&params, &world, &mut s, Buttons::RIGHT);
+        }
+        assert_eq!((s.y, s.vy), (300.0, 0.0));
+        assert!(s.x > 100.0);
+    }
+
+    #[test]
+    fn trajectory_ends_at_first_solid_contact() {
+        let world = [
+            Rect { x: 0.0, y: 480.0, w: 960.0, h: 60.0 },
+            Rect { x: 300.0, y: 380.0, w: 120.0, h: 20.0 },
+        ];
+        let params = Params { world_wrap_mode: 0.0, ..Params::default() };
+        let start = State { x: 100.0, y: 436.0, w: 28.0, h: 44.0, grounded: 1, ..State::default() };
+        let buttons = Buttons::RIGHT | Buttons::JUMP;
+
+        let arc = trajectory(&params, &world, start, buttons, 240);
+        assert!(arc.len() > 1 && arc.len() < 240);
+        let landing = predict_landing(&params, &world, &start, buttons, 240).unwrap();
+        assert_eq!(*arc.last().unwrap(), (landing.0, landing.1));
+        assert_eq!(arc.len() as u32, landing.2);
+        assert!(arc.iter().any(|&(_, y)| y < 436.0));
+
+        // Jumping up into the platform's underside stops at the bonk.
+        let under = State { x: 340.0, ..start };
+        let arc = trajectory(&params, &world, under, Buttons::JUMP, 240);
+        let mut s = under;
+        let bonk = (1..=240).find(|_| step(&params, &world, &mut s, Buttons::JUMP).bonked != 0).unwrap();
+        assert_eq!(arc.len(), bonk);
+        assert_eq!(arc.last().unwrap().1, 400.0);
+    }
+
+    #[test]
+    fn min_jump_velocity_floors_tap_hop() {
+        let floor = [Rect { x: 0.0, y: 480.0, w: 400.0, h: 20.0 }];
+        let tap_apex = |params: &Params, hold: u32| {
+            let mut s = State { x: 100.0, y: 436.0, w: 28.0, h: 44.0, grounded: 1, ..State::default() };
+            for frame in 0..90 {
+                let buttons = if frame < hold { Buttons::JUMP } else { Buttons::empty() };
+                step(params, &floor, &mut s, buttons);
+            }
+            s.last_jump_apex_height
+        };
+
+        let base = Params { world_wrap_mode: 0.0, jump_cut_multiplier: 0.3, ..Params::default() };
+        let floored = Params { min_jump_velocity: 500.0, ..base };
+        // Rising at least 500 px/s under gravity_up reaches ~v^2 / 2g.
+        let min_apex = 500.0 * 500.0 / (2.0 * floored.gravity_up);
+
+        let taps: Vec<f32> = (1..=3).map(|hold| tap_apex(&floored, hold)).collect();
+        for &apex in &taps {
+            assert!(apex >= min_apex * 0.9, "apex {apex} < {min_apex}");
+            assert!(apex > tap_apex(&base, 1));
+        }
+        assert!(taps.iter().fold(0.0f32, |m, &a| m.max((a - taps[0]).abs())) <= 20.0, "{taps:?}");
+
+        // A floor above the full jump never speeds a cut up.
+        let huge = Params { min_jump_velocity: 5000.0, ..base };
+        assert_eq!(tap_apex(&huge, 1), tap_apex(&Params { jump_cut_multiplier: 1.0, ..base }, 90));
+    }
+
+    #[test]
+    fn wrap_regrounds_on_new_side_and_refuses_embedding() {
+        let params = Params { world_w: 200.0, world_wrap_mode: 1.0, ..Params::default() };
+        let right_floor = Rect { x: 100.0, y: 480.0, w: 100.0, h: 20.0 };
+        let start = State { x: 170.0, y: 436.0, w: 28.0, h: 44.0, grounded: 1, ..State::default() };
+        let walk_to_seam = |params: &Params, world: &[Rect]| {
+            let mut s = start;
+            for _ in 0..30 {
+                let before = s.x;
+                let ev = step(params, world, &mut s, Buttons::RIGHT);
+                if s.x < before || s.x + s.w > params.world_w {
+                    return (s, ev);
+                }
+            }
+            panic!("never reached the seam");
+        };
+
+        // Nothing under the left edge: the wrapped player is airborne at once.
+        let (s, ev) = walk_to_seam(&params, &[right_floor]);
+        assert_eq!((s.x, s.grounded, ev.left_ground, ev.landed), (0.0, 0, 1, 0));
+
+        // A ledge at a different height on the left: grounded there instead.
+        let left_ledge = Rect { x: 0.0, y: 480.0, w: 40.0, h: 20.0 };
+        let (s, ev) = walk_to_seam(&params, &[right_floor, left_ledge]);
+        assert_eq!((s.x, s.y, s.grounded, ev.left_ground), (0.0, 436.0, 1, 0));
+
+        // A wall where the player would land: the torus wrap is refused (mode 1
+        // wraps regardless, like pygame; see PYGAME_WALL_WRAP_TRACE).
+        let wall = Rect { x: 0.0, y: 400.0, w: 40.0, h: 80.0 };
+        let torus = Params { world_wrap_mode: 2.0, ..params };
+        let (s, _) = walk_to_seam(&torus, &[right_floor, wall]);
+        assert!(s.x > 172.0 && s.x + s.w > params.world_w);
+        assert_eq!(s.grounded, 1);
+        assert!(!rects_intersect(&s.hitbox(), &wall));
+    }
+
+    #[test]
+    fn input_deadzone_zeroes_and_rescales_move_x() {
+        let floor = [Rect { x: 0.0, y: 480.0, w: 4000.0, h: 20.0 }];
+        let params = Params { world_wrap_mode: 0.0, input_deadzone: 0.2, ..Params::default() };
+        let start = State { x: 2000.0, y: 436.0, w: 28.0, h: 44.0, grounded: 1, ..State::default() };
+        let settle = |params: &Params, move_x: f32| {
+            let mut s = start;
+            let input = Input { move_x, ..Input::default() };
+            for _ in 0..90 {
+                step_input(params, &floor, &mut s, &input);
+            }
+            s
+        };
+
+        let inside = settle(&params, 0.15);
+        assert_eq!((inside.x, inside.vx), (2000.0, 0.0));
+
+        // 0.3 sits 1/8 of the way through the live range [0.2, 1].
+        let above = settle(&params, 0.3);
+        approx_eq(above.vx, params.ground_max_speed * 0.125);
+        let full = settle(&params, -1.0);
+        approx_eq(full.vx, -params.ground_max_speed);
+
+        // Default: no deadzone, raw value used.
+        let raw = settle(&Params { input_deadzone: 0.0, ..params }, 0.3);
+        approx_eq(raw.vx, params.ground_max_speed * 0.3);
+    }
+
+    #[test]
+    fn input_smoothing_eases_toward_stick() {
+        let floor = [Rect { x: 0.0, y: 480.0, w: 4000.0, h: 20.0 }];
+        let params = Params { world_wrap_mode: 0.0, input_smoothing: 0.5, ..Params::default() };
+        let mut s = State { x: 100.0, y: 436.0, w: 28.0, h: 44.0, grounded: 1, ..State::default() };
+        let input = Input { move_x: 1.0, ..Input::default() };
+
+        let mut seen = Vec::new();
+        for _ in 0..4 {
+            step_input(&params, &floor, &mut s, &input);
+            seen.push(s.move_x_smoothed);
+        }
+        assert_eq!(seen, [0.5, 0.75, 0.875, 0.9375]);
+
+        // Released: decays and then settles to exactly 0.
+        for _ in 0..20 {
+            step_input(&params, &floor, &mut s, &Input::default());
+        }
+        assert_eq!(s.move_x_smoothed, 0.0);
+    }
+
+    /// `(x, y, grounded)` per frame from the pygame sandbox:
+    /// `tuner.py --trace-in` on the default trace params with `world_w = 200.5`,
+    /// `ground_max_speed = 900`, `ground_accel = 6000`, `world_wrap_mode = 1`,
+    /// a 28x44 player at x = 100 on a wide floor, inputs RIGHT x16 then LEFT x44.
+    const PYGAME_SEAM_TRACE: [(f32, f32, u8); 60] = [
+        (102.0, 436.0, 1), (105.0, 436.0, 1), (110.0, 436.0, 1), (116.0, 436.0, 1), (124.0, 436.0, 1),
+        (134.0, 436.0, 1), (146.0, 436.0, 1), (158.0, 436.0, 1), (0.0, 436.0, 1), (15.0, 436.0, 1),
+        (30.0, 436.0, 1), (45.0, 436.0, 1), (60.0, 436.0, 1), (75.0, 436.0, 1), (90.0, 436.0, 1),
+        (105.0, 436.0, 1), (120.0, 436.0, 1), (135.0, 436.0, 1), (147.0, 436.0, 1), (159.0, 436.0, 1),
+        (171.0, 436.0, 1), (0.0, 436.0, 1), (10.0, 436.0, 1), (20.0, 436.0, 1), (30.0, 436.0, 1),
+        (38.0, 436.0, 1), (46.0, 436.0, 1), (54.0, 436.0, 1), (62.0, 436.0, 1), (68.0, 436.0, 1),
+        (74.0, 436.0, 1), (79.0, 436.0, 1), (84.0, 436.0, 1), (88.0, 436.0, 1), (91.0, 436.0, 1),
+        (94.0, 436.0, 1), (96.0, 436.0, 1), (98.0, 436.0, 1), (99.0, 436.0, 1), (99.0, 436.0, 1),
+        (99.0, 436.0, 1), (97.0, 436.0, 1), (93.0, 436.0, 1), (88.0, 436.0, 1), (82.0, 436.0, 1),
+        (74.0, 436.0, 1), (64.0, 436.0, 1), (52.0, 436.0, 1), (37.0, 436.0, 1), (22.0, 436.0, 1),
+        (7.0, 436.0, 1), (172.0, 436.0, 1), (157.0, 436.0, 1), (142.0, 436.0, 1), (127.0, 436.0, 1),
+        (112.0, 436.0, 1), (97.0, 436.0, 1), (82.0, 436.0, 1), (67.0, 436.0, 1), (52.0, 436.0, 1),
+    ];
+
+    #[test]
+    fn pygame_legacy_wrap_matches_captured_trace() {
+        let params = Params {
+            world_w: 200.5,
+            ground_max_speed: 900.0,
+            ground_accel: 6000.0,
+            world_wrap_mode: 1.0,
+            ..Params::default()
+        };
+        let world = [Rect { x: -400.0, y: 480.0, w: 1200.0, h: 60.0 }];
+        let mut s = State { x: 100.0, y: 436.0, w: 28.0, h: 44.0, grounded: 1, ..State::default() };
+
+        for (frame, &(x, y, grounded)) in PYGAME_SEAM_TRACE.iter().enumerate() {
+            let buttons = if frame < 16 { Buttons::RIGHT } else { Buttons::LEFT };
+            step(&params, &world, &mut s, buttons);
+            assert_eq!((s.x, s.y, s.grounded), (x, y, grounded), "frame {frame}");
+        }
+    }
+
+    /// `tuner.py --trace-in` on the default trace params with `world_w = 200`,
+    /// `ground_max_speed = 900`, `ground_accel = 6000`, `world_wrap_mode = 1`, a
+    /// wide floor and a wall at x 0..40, y 400..480 right where the wrap lands; a
+    /// 28x44 player at x = 100, inputs RIGHT x12, none x8, LEFT x10. pygame wraps
+    /// into the wall and gets pushed back across the seam every frame.
+    const PYGAME_WALL_WRAP_TRACE: [(f32, f32, u8); 30] = [
+        (102.0, 436.0, 1), (105.0, 436.0, 1), (110.0, 436.0, 1), (116.0, 436.0, 1), (124.0, 436.0, 1),
+        (134.0, 436.0, 1), (146.0, 436.0, 1), (158.0, 436.0, 1), (0.0, 436.0, 1), (172.0, 436.0, 1),
+        (0.0, 436.0, 1), (172.0, 436.0, 1), (0.0, 436.0, 1), (172.0, 436.0, 1), (0.0, 436.0, 1),
+        (172.0, 436.0, 1), (0.0, 436.0, 1), (172.0, 436.0, 1), (0.0, 436.0, 1), (172.0, 436.0, 1),
+        (0.0, 436.0, 1), (172.0, 436.0, 1), (0.0, 436.0, 1), (172.0, 436.0, 1), (0.0, 436.0, 1),
+        (172.0, 436.0, 1), (0.0, 436.0, 1), (172.0, 436.0, 1), (0.0, 436.0, 1), (172.0, 436.0, 1),
+    ];
+
+    #[test]
+    fn pygame_legacy_wrap_into_wall_matches_captured_trace() {
+        let params = Params {
+            world_w: 200.0,
+            ground_max_speed: 900.0,
+            ground_accel: 6000.0,
+            world_wrap_mode: 1.0,
+            ..Params::default()
+        };
+        let world = [
+            Rect { x: -400.0, y: 480.0, w: 1200.0, h: 60.0 },
+            Rect { x: 0.0, y: 400.0, w: 40.0, h: 80.0 },
+        ];
+        let mut s = State { x: 100.0, y: 436.0, w: 28.0, h: 44.0, grounded: 1, ..State::default() };
+
+        for (frame, &(x, y, grounded)) in PYGAME_WALL_WRAP_TRACE.iter().enumerate() {
+            let buttons = match frame {
+                0..12 => Buttons::RIGHT,
+                12..20 => Buttons::empty(),
+                _ => Buttons::LEFT,
+            };
+            step(&params, &world, &mut s, buttons);
+            assert_eq!((s.x, s.y, s.grounded), (x, y, grounded), "frame {frame}");
+        }
+    }
+
+    #[test]
+    fn wrap_pygame_legacy_rounds_half_to_even() {
+        // 200.5 -> 200 (not 201), like Python's round.
+        assert_eq!(wrap_pygame_legacy(173.0, 28.0, 200.5), 0.0);
+        assert_eq!(wrap_pygame_legacy(-1.0, 28.0, 200.5), 172.0);
+        assert_eq!(wrap_pygame_legacy(172.0, 28.0, 200.5), 172.0);
+        // 28.5 -> 28 wide.
+        assert_eq!(wrap_pygame_legacy(172.0, 28.5, 200.0), 172.0);
+    }
+
+    #[test]
+    fn events_mask_mirrors_flag_fields() {
+        assert_eq!(Events::default().mask(), EventMask::empty());
+        let ev = Events { jumped: 1, left_ground: 1, ground_reason: GroundReason::Snap as u8, numeric_fault: 1, ..Events::default() };
+        assert_eq!(ev.mask(), EventMask::JUMPED | EventMask::LEFT_GROUND | EventMask::GROUND_REASON);
+    }
+
+    #[test]
+    fn wall_proximity_gravity_scale_slows_fall_near_wall() {
+        let wall = [Rect { x: 200.0, y: 0.0, w: 40.0, h: 2000.0 }];
+        let params = Params { world_wrap_mode: 0.0, wall_proximity_gravity_scale: 0.5, ..Params::default() };
+        let fall = |params: &Params, x: f32| {
+            let mut s = State { x, y: 100.0, w: 28.0, h: 44.0, ..State::default() };
+            for _ in 0..20 {
+                step(params, &wall, &mut s, Buttons::empty());
+            }
+            s.y - 100.0
+        };
+
+        let beside = fall(&params, 200.0 - 28.0 - 2.0);
+        let open = fall(&params, 40.0);
+        assert!(beside < open * 0.6, "beside {beside} vs open {open}");
+
+        // Default 1.0 leaves the fall alone.
+        let default = Params { wall_proximity_gravity_scale: 1.0, ..params };
+        assert_eq!(fall(&default, 200.0 - 28.0 - 2.0), open);
+    }
+
+    #[test]
+    fn depenetrate_frees_rect_spawned_inside_walls() {
+        let wall = Rect { x: 100.0, y: 0.0, w: 40.0, h: 200.0 };
+        // Mostly inside the wall's right half: out through the right face.
+        let mut r = Rect { x: 125.0, y: 50.0, w: 20.0, h: 40.0 };
+        depenetrate(&mut r, &[wall]);
+        assert_eq!((r.x, r.y), (140.0, 50.0));
+
+        // Already free: untouched.
+        let mut free = Rect { x: 10.0, y: 10.0, w: 20.0, h: 20.0 };
+        depenetrate(&mut free, &[wall]);
+        assert_eq!((free.x, free.y), (10.0, 10.0));
+
+        // Jammed into the corner of a wall and a floor inside a closed room: every
+        // single-face exit hits other geometry, so the MTV passes walk it out.
+        let room = [
+            Rect { x: 0.0, y: 0.0, w: 50.0, h: 100.0 },
+            Rect { x: 0.0, y: 100.0, w: 200.0, h: 50.0 },
+            Rect { x: 0.0, y: -100.0, w: 200.0, h: 100.0 },
+            Rect { x: -100.0, y: -100.0, w: 100.0, h: 350.0 },
+            Rect { x: -100.0, y: 150.0, w: 400.0, h: 100.0 },
+            Rect { x: 200.0, y: -100.0, w: 100.0, h: 350.0 },
+        ];
+        let mut wedged = Rect { x: 45.0, y: 95.0, w: 20.0, h: 20.0 };
+        depenetrate(&mut wedged, &room);
+        assert_eq!((wedged.x, wedged.y), (50.0, 80.0));
+        assert!(!room.iter().any(|p| rects_intersect(&wedged, p)));
+    }
+
+    #[test]
+    fn pre_collision_velocity_shows_what_collision_changed() {
+        let world = [
+            Rect { x: 0.0, y: 480.0, w: 400.0, h: 20.0 },
+            Rect { x: 0.0, y: 380.0, w: 400.0, h: 20.0 },
+            Rect { x: 300.0, y: 400.0, w: 40.0, h: 80.0 },
+        ];
+        let params = Params { world_wrap_mode: 0.0, ..Params::default() };
+
+        // Jumping into the low ceiling: collision kills the upward speed.
+        let mut s = State { x: 100.0, y: 436.0, w: 28.0, h: 44.0, grounded: 1, ..State::default() };
+        let ev = (0..30).map(|_| step(&params, &world, &mut s, Buttons::JUMP)).find(|ev| ev.bonked != 0).unwrap();
+        assert!(ev.vy_pre_collision < 0.0);
+        assert_eq!(s.vy, 0.0);
+
+        // Running into the wall: the resolver stops the rect but keeps vx.
+        let mut s = State { x: 272.0, y: 436.0, w: 28.0, h: 44.0, vx: 200.0, grounded: 1, ..State::default() };
+        let ev = step(&params, &world, &mut s, Buttons::RIGHT);
+        assert!(ev.vx_pre_collision > 0.0);
+        assert_eq!((s.x, s.vx), (272.0, ev.vx_pre_collision));
+        assert_eq!(s.pushing, 1);
+    }
+
+    #[test]
+    fn auto_fast_fall_after_speeds_descent() {
+        let params = Params { world_wrap_mode: 0.0, terminal_velocity: 5000.0, ..Params::default() };
+        let auto = Params { auto_fast_fall_after: 20.0, ..params };
+        let fall = |params: &Params| {
+            let mut s = State { x: 0.0, y: 0.0, w: 28.0, h: 44.0, vy: 1.0, ..State::default() };
+            let mut fast = Vec::new();
+            let vys: Vec<f32> = (0..40)
+                .map(|_| {
+                    fast.push(step(params, &[], &mut s, Buttons::empty()).fast_falling);
+                    s.vy
+                })
+                .collect();
+            (vys, fast)
+        };
+
+        let (plain, _) = fall(&params);
+        let (assisted, fast) = fall(&auto);
+        // Identical until airtime passes the threshold, then faster.
+        assert_eq!(plain[..20], assisted[..20]);
+        assert!(fast[..20].iter().all(|&f| f == 0) && fast[21..].iter().all(|&f| f == 1));
+        assert!(assisted[39] > plain[39]);
+        let dv = |v: &[f32]| v[39] - v[38];
+        approx_eq(dv(&assisted), dv(&plain) * params.fast_fall_multiplier);
+    }
+
+    #[test]
+    fn surface_behavior_runs_on_contacts() {
+        use std::cell::RefCell;
+
+        /// Sticky rect: kills all velocity on touch; logs every contact.
+        struct Sticky {
+            index: usize,
+            log: RefCell<Vec<(usize, Face)>>,
+        }
+        impl SurfaceBehavior for Sticky {
+            fn on_contact(&self, state: &mut State, face: Face, index: usize) {
+                self.log.borrow_mut().push((index, face));
+                if index == self.index {
+                    state.vx = 0.0;
+                    state.vy = 0.0;
+                }
+            }
+        }
+
+        let world = [
+            Rect { x: 0.0, y: 480.0, w: 400.0, h: 20.0 },
+            Rect { x: 300.0, y: 300.0, w: 40.0, h: 180.0 },
+        ];
+        let params = Params { world_wrap_mode: 0.0, ..Params::default() };
+        let sticky = Sticky { index: 1, log: RefCell::new(Vec::new()) };
+
+        // Running into the wall: plain `step` keeps vx, the behavior zeroes it.
+        let start = State { x: 270.0, y: 436.0, w: 28.0, h: 44.0, vx: 240.0, grounded: 1, ..State::default() };
+        let mut plain = start;
+        step(&params, &world, &mut plain, Buttons::RIGHT);
+        let mut hooked = start;
+        step_with_surfaces(&params, &world, &mut hooked, Buttons::RIGHT, &sticky);
+        assert!(plain.vx > 0.0);
+        assert_eq!((hooked.x, hooked.vx), (plain.x, 0.0));
+        assert!(sticky.log.borrow().contains(&(1, Face::Left)));
+
+        // Landing on the floor reports its top face but leaves vx alone.
+        sticky.log.borrow_mut().clear();
+        let mut falling = State { x: 100.0, y: 430.0, w: 28.0, h: 44.0, vx: 100.0, vy: 600.0, ..State::default() };
+        step_with_surfaces(&params, &world, &mut falling, Buttons::empty(), &sticky);
+        assert_eq!(sticky.log.borrow().as_slice(), [(0, Face::Top)]);
+        assert!(falling.vx > 0.0);
+    }
+
+    #[test]
+    fn surface_behavior_can_teleport() {
+        /// Portal: touching rect 1 from either side moves the player to (600, 236).
+        struct Portal;
+        impl SurfaceBehavior for Portal {
+            fn on_contact(&self, state: &mut State, face: Face, index: usize) {
+                if index == 1 && matches!(face, Face::Left | Face::Right) {
+                    (state.x, state.y) = (600.0, 236.0);
+                }
+            }
+        }
+
+        let world = [
+            Rect { x: 0.0, y: 480.0, w: 960.0, h: 20.0 },
+            Rect { x: 300.0, y: 300.0, w: 40.0, h: 180.0 },
+            Rect { x: 560.0, y: 280.0, w: 120.0, h: 20.0 },
+        ];
+        let params = Params { world_wrap_mode: 0.0, ..Params::default() };
+        let start = State { x: 270.0, y: 436.0, w: 28.0, h: 44.0, vx: 240.0, grounded: 1, ..State::default() };
+        let mut s = start;
+        step_with_surfaces(&params, &world, &mut s, Buttons::RIGHT, &Portal);
+        assert_eq!((s.x, s.y), (600.0, 236.0));
+        assert_eq!(s.grounded, 1);
+        // Only the walk up to the wall counts as distance.
+        assert!(s.distance_x < 4.0);
+
+        // A hitbox offset is kept: the portal moves the sprite, and the box follows it.
+        let mut offset = State { hitbox_offset_x: 4.0, hitbox_w: 20.0, x: 276.0, ..start };
+        step_with_surfaces(&params, &world, &mut offset, Buttons::RIGHT, &Portal);
+        assert_eq!((offset.x, offset.y), (600.0, 236.0));
+    }
 }
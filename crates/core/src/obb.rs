@@ -0,0 +1,85 @@
+//! Oriented bounding boxes for decorative slopes and rotated blocks.
+//!
+//! The player is always an AABB, so AABB-vs-OBB needs only four separating
+//! axes: the world X/Y axes and the OBB's two local axes.
+
+use crate::{rects_intersect, Rect};
+
+/// A rect rotated by `angle` radians about its center.
+#[repr(C)]
+#[derive(Copy, Clone, Debug, Default)]
+pub struct ObbRect {
+    pub cx: f32,
+    pub cy: f32,
+    /// Half width along the local X axis.
+    pub hw: f32,
+    /// Half height along the local Y axis.
+    pub hh: f32,
+    pub angle: f32,
+}
+
+impl ObbRect {
+    /// Local unit axes `(u, v)`.
+    #[inline]
+    fn axes(&self) -> ((f32, f32), (f32, f32)) {
+        let (sin, cos) = self.angle.sin_cos();
+        ((cos, sin), (-sin, cos))
+    }
+
+    /// Axis-aligned bounds, used as a cheap reject before SAT.
+    pub fn bounds(&self) -> Rect {
+        let ((ux, uy), (vx, vy)) = self.axes();
+        let ex = self.hw * ux.abs() + self.hh * vx.abs();
+        let ey = self.hw * uy.abs() + self.hh * vy.abs();
+        Rect { x: self.cx - ex, y: self.cy - ey, w: 2.0 * ex, h: 2.0 * ey }
+    }
+}
+
+/// Minimum translation vector that moves `rect` out of `obb`, or `None` when
+/// they don't overlap (touching counts as not overlapping).
+pub fn aabb_obb_mtv(rect: &Rect, obb: &ObbRect) -> Option<(f32, f32)> {
+    if !rects_intersect(rect, &obb.bounds()) {
+        return None;
+    }
+
+    let (u, v) = obb.axes();
+    let rcx = rect.x + 0.5 * rect.w;
+    let rcy = rect.y + 0.5 * rect.h;
+    let (rhw, rhh) = (0.5 * rect.w, 0.5 * rect.h);
+    let (dx, dy) = (rcx - obb.cx, rcy - obb.cy);
+
+    // World axes first so ties resolve like the AABB resolver.
+    let mut best: Option<(f32, (f32, f32))> = None;
+    for (ax, ay) in [(1.0, 0.0), (0.0, 1.0), u, v] {
+        let r_aabb = rhw * ax.abs() + rhh * ay.abs();
+        let r_obb = obb.hw * (u.0 * ax + u.1 * ay).abs() + obb.hh * (v.0 * ax + v.1 * ay).abs();
+        let dist = dx * ax + dy * ay;
+        let overlap = r_aabb + r_obb - dist.abs();
+        if overlap <= 0.0 {
+            return None;
+        }
+        let dir = if dist < 0.0 { -1.0 } else { 1.0 };
+        if best.is_none_or(|(o, _)| overlap < o) {
+            best = Some((overlap, (ax * dir, ay * dir)));
+        }
+    }
+
+    best.map(|(overlap, (nx, ny))| (nx * overlap, ny * overlap))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{aabb_obb_mtv, ObbRect};
+    use crate::Rect;
+
+    #[test]
+    fn axis_aligned_obb_matches_aabb_overlap() {
+        let obb = ObbRect { cx: 50.0, cy: 50.0, hw: 10.0, hh: 10.0, angle: 0.0 };
+        let rect = Rect { x: 35.0, y: 45.0, w: 10.0, h: 10.0 };
+        let (mx, my) = aabb_obb_mtv(&rect, &obb).unwrap();
+        assert!((mx + 5.0).abs() < 1e-4 && my.abs() < 1e-4, "{mx},{my}");
+
+        let apart = Rect { x: 30.0, y: 45.0, w: 10.0, h: 10.0 };
+        assert!(aabb_obb_mtv(&apart, &obb).is_none());
+    }
+}
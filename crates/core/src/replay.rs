@@ -0,0 +1,576 @@
+//! Compact binary replay format.
+//!
+//! Layout (all integers and floats little-endian):
+//!
+//! ```text
+//! magic        b"PLRP"
+//! version      u16            (REPLAY_VERSION)
+//! param_count  u16            (PARAM_NAMES.len())
+//! params       f32 * param_count, in `Params` declaration order
+//! rect_count   u32
+//! rects        (x, y, w, h: f32) * rect_count
+//! state        `State` fields in declaration order (f32 / u8 / i8 / u32)
+//! input_count  u32
+//! inputs       u8 * input_count (raw `Buttons` bits, one per frame)
+//! ```
+//!
+//! `version` is bumped whenever the `State` encoding or the `Params` field order
+//! changes; `param_count` guards the `Params` block size. Readers reject any
+//! other version, so an old file is never read into the wrong fields.
+//!
+//! [`serialize_sim`] savestates reuse the same blocks under their own magic:
+//! `b"PLSV"`, version, params, world, state (no inputs).
+
+use std::io::{self, Read, Write};
+
+use crate::{step, Buttons, Params, Rect, State, PARAM_NAMES};
+
+pub const REPLAY_MAGIC: [u8; 4] = *b"PLRP";
+pub const REPLAY_VERSION: u16 = 13;
+pub const SIM_MAGIC: [u8; 4] = *b"PLSV";
+
+/// Everything needed to reproduce a run: params, world, start state, and one
+/// input byte per 60Hz frame.
+#[derive(Clone, Debug)]
+pub struct Replay {
+    pub params: Params,
+    pub world: Vec<Rect>,
+    pub initial_state: State,
+    pub inputs: Vec<u8>,
+}
+
+fn invalid(msg: &str) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, msg.to_string())
+}
+
+fn put_f32<W: Write>(w: &mut W, v: f32) -> io::Result<()> {
+    w.write_all(&v.to_le_bytes())
+}
+
+fn put_u8<W: Write>(w: &mut W, v: u8) -> io::Result<()> {
+    w.write_all(&[v])
+}
+
+fn put_u32<W: Write>(w: &mut W, v: u32) -> io::Result<()> {
+    w.write_all(&v.to_le_bytes())
+}
+
+fn put_i8<W: Write>(w: &mut W, v: i8) -> io::Result<()> {
+    w.write_all(&v.to_le_bytes())
+}
+
+fn get_f32<R: Read>(r: &mut R) -> io::Result<f32> {
+    let mut b = [0u8; 4];
+    r.read_exact(&mut b)?;
+    Ok(f32::from_le_bytes(b))
+}
+
+fn get_u8<R: Read>(r: &mut R) -> io::Result<u8> {
+    let mut b = [0u8; 1];
+    r.read_exact(&mut b)?;
+    Ok(b[0])
+}
+
+fn get_i8<R: Read>(r: &mut R) -> io::Result<i8> {
+    Ok(get_u8(r)? as i8)
+}
+
+fn get_u16<R: Read>(r: &mut R) -> io::Result<u16> {
+    let mut b = [0u8; 2];
+    r.read_exact(&mut b)?;
+    Ok(u16::from_le_bytes(b))
+}
+
+fn get_u32<R: Read>(r: &mut R) -> io::Result<u32> {
+    let mut b = [0u8; 4];
+    r.read_exact(&mut b)?;
+    Ok(u32::from_le_bytes(b))
+}
+
+pub(crate) fn write_params<W: Write>(w: &mut W, params: &Params) -> io::Result<()> {
+    w.write_all(&(PARAM_NAMES.len() as u16).to_le_bytes())?;
+    for &v in params.as_slice() {
+        put_f32(w, v)?;
+    }
+    Ok(())
+}
+
+pub(crate) fn read_params<R: Read>(r: &mut R) -> io::Result<Params> {
+    if get_u16(r)? as usize != PARAM_NAMES.len() {
+        return Err(invalid("params field count mismatch"));
+    }
+    let mut params = Params::default();
+    for v in params.as_mut_slice() {
+        *v = get_f32(r)?;
+    }
+    Ok(params)
+}
+
+pub(crate) fn write_world<W: Write>(w: &mut W, world: &[Rect]) -> io::Result<()> {
+    w.write_all(&(world.len() as u32).to_le_bytes())?;
+    for rect in world {
+        for v in [rect.x, rect.y, rect.w, rect.h] {
+            put_f32(w, v)?;
+        }
+    }
+    Ok(())
+}
+
+pub(crate) fn read_world<R: Read>(r: &mut R) -> io::Result<Vec<Rect>> {
+    let count = get_u32(r)? as usize;
+    let mut world = Vec::with_capacity(count.min(1 << 16));
+    for _ in 0..count {
+        world.push(Rect { x: get_f32(r)?, y: get_f32(r)?, w: get_f32(r)?, h: get_f32(r)? });
+    }
+    Ok(world)
+}
+
+pub(crate) fn write_state<W: Write>(w: &mut W, s: &State) -> io::Result<()> {
+    put_f32(w, s.x)?;
+    put_f32(w, s.y)?;
+    put_f32(w, s.vx)?;
+    put_f32(w, s.vy)?;
+    put_f32(w, s.w)?;
+    put_f32(w, s.h)?;
+    put_u8(w, s.grounded)?;
+    put_f32(w, s.coyote)?;
+    put_f32(w, s.jump_buffer)?;
+    put_u8(w, s.jump_was_down)?;
+    put_u8(w, s.snap_suppress)?;
+    put_f32(w, s.run_ramp)?;
+    put_f32(w, s.hitbox_offset_x)?;
+    put_f32(w, s.hitbox_offset_y)?;
+    put_f32(w, s.hitbox_w)?;
+    put_f32(w, s.hitbox_h)?;
+    put_u8(w, s.just_landed)?;
+    put_u32(w, s.airtime_frames)?;
+    put_u32(w, s.max_airtime_frames)?;
+    put_f32(w, s.max_fall_speed)?;
+    put_f32(w, s.distance_x)?;
+    put_f32(w, s.jump_launch_y)?;
+    put_u8(w, s.jump_active)?;
+    put_f32(w, s.last_jump_apex_height)?;
+    put_i8(w, s.pushing)?;
+    put_f32(w, s.ground_normal_x)?;
+    put_f32(w, s.ground_normal_y)?;
+    put_f32(w, s.fall_distance)?;
+    put_f32(w, s.move_x_smoothed)?;
+    put_u8(w, s.grounded_recently)?;
+    put_u8(w, s.jump_cut_timer)?;
+    Ok(())
+}
+
+pub(crate) fn read_state<R: Read>(r: &mut R) -> io::Result<State> {
+    Ok(State {
+        x: get_f32(r)?,
+        y: get_f32(r)?,
+        vx: get_f32(r)?,
+        vy: get_f32(r)?,
+        w: get_f32(r)?,
+        h: get_f32(r)?,
+        grounded: get_u8(r)?,
+        coyote: get_f32(r)?,
+        jump_buffer: get_f32(r)?,
+        jump_was_down: get_u8(r)?,
+        snap_suppress: get_u8(r)?,
+        run_ramp: get_f32(r)?,
+        hitbox_offset_x: get_f32(r)?,
+        hitbox_offset_y: get_f32(r)?,
+        hitbox_w: get_f32(r)?,
+        hitbox_h: get_f32(r)?,
+        just_landed: get_u8(r)?,
+        airtime_frames: get_u32(r)?,
+        max_airtime_frames: get_u32(r)?,
+        max_fall_speed: get_f32(r)?,
+        distance_x: get_f32(r)?,
+        jump_launch_y: get_f32(r)?,
+        jump_active: get_u8(r)?,
+        last_jump_apex_height: get_f32(r)?,
+        pushing: get_i8(r)?,
+        ground_normal_x: get_f32(r)?,
+        ground_normal_y: get_f32(r)?,
+        fall_distance: get_f32(r)?,
+        move_x_smoothed: get_f32(r)?,
+        grounded_recently: get_u8(r)?,
+        jump_cut_timer: get_u8(r)?,
+    })
+}
+
+/// Writes `replay` in the binary format described in the module docs.
+pub fn write_replay_bin<W: Write>(mut w: W, replay: &Replay) -> io::Result<()> {
+    w.write_all(&REPLAY_MAGIC)?;
+    w.write_all(&REPLAY_VERSION.to_le_bytes())?;
+    write_params(&mut w, &replay.params)?;
+    write_world(&mut w, &replay.world)?;
+    write_state(&mut w, &replay.initial_state)?;
+    w.write_all(&(replay.inputs.len() as u32).to_le_bytes())?;
+    w.write_all(&replay.inputs)?;
+    Ok(())
+}
+
+/// Reads a replay written by [`write_replay_bin`]. Fails with
+/// `ErrorKind::InvalidData` on a bad magic, unknown version, or params layout.
+pub fn read_replay_bin<R: Read>(mut r: R) -> io::Result<Replay> {
+    let mut magic = [0u8; 4];
+    r.read_exact(&mut magic)?;
+    if magic != REPLAY_MAGIC {
+        return Err(invalid("not a platlab binary replay"));
+    }
+    if get_u16(&mut r)? != REPLAY_VERSION {
+        return Err(invalid("unsupported replay version"));
+    }
+    let params = read_params(&mut r)?;
+    let world = read_world(&mut r)?;
+    let initial_state = read_state(&mut r)?;
+    // Read through `take` so a bogus count can't force a huge allocation up front.
+    let count = get_u32(&mut r)? as u64;
+    let mut inputs = Vec::new();
+    r.take(count).read_to_end(&mut inputs)?;
+    if (inputs.len() as u64) < count {
+        return Err(io::Error::new(io::ErrorKind::UnexpectedEof, "replay inputs are truncated"));
+    }
+    Ok(Replay { params, world, initial_state, inputs })
+}
+
+/// Why [`deserialize_sim`] rejected a blob.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum SimError {
+    /// Doesn't start with [`SIM_MAGIC`].
+    BadMagic,
+    /// Written with a different `State` encoding (carries the blob's version).
+    UnsupportedVersion(u16),
+    /// The params block has a different field count than this build's `Params`.
+    ParamsMismatch,
+    /// Ended before the state block was complete.
+    Truncated,
+    /// Extra bytes after the state block.
+    TrailingBytes,
+}
+
+impl std::fmt::Display for SimError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            SimError::BadMagic => write!(f, "not a platlab savestate"),
+            SimError::UnsupportedVersion(v) => write!(f, "unsupported savestate version {v} (expected {REPLAY_VERSION})"),
+            SimError::ParamsMismatch => write!(f, "params field count mismatch"),
+            SimError::Truncated => write!(f, "savestate is truncated"),
+            SimError::TrailingBytes => write!(f, "trailing bytes after savestate"),
+        }
+    }
+}
+
+impl std::error::Error for SimError {}
+
+/// Snapshot of the whole simulation (params, world in order, state) as a
+/// compact, platform-independent blob: the rollback/savestate format.
+pub fn serialize_sim(params: &Params, world: &[Rect], state: &State) -> Vec<u8> {
+    let mut out = Vec::with_capacity(8 + 4 * PARAM_NAMES.len() + 4 + 16 * world.len() + 128);
+    out.extend_from_slice(&SIM_MAGIC);
+    out.extend_from_slice(&REPLAY_VERSION.to_le_bytes());
+    // Writing to a Vec cannot fail.
+    let _ = write_params(&mut out, params)
+        .and_then(|_| write_world(&mut out, world))
+        .and_then(|_| write_state(&mut out, state));
+    out
+}
+
+/// Restores a blob from [`serialize_sim`]. The whole slice must be one savestate.
+pub fn deserialize_sim(bytes: &[u8]) -> Result<(Params, Vec<Rect>, State), SimError> {
+    let mut r = bytes;
+    // The readers only fail on a short read, or (params) on a count mismatch.
+    let short = |e: io::Error| {
+        if e.kind() == io::ErrorKind::UnexpectedEof { SimError::Truncated } else { SimError::ParamsMismatch }
+    };
+
+    let mut magic = [0u8; 4];
+    r.read_exact(&mut magic).map_err(short)?;
+    if magic != SIM_MAGIC {
+        return Err(SimError::BadMagic);
+    }
+    let version = get_u16(&mut r).map_err(short)?;
+    if version != REPLAY_VERSION {
+        return Err(SimError::UnsupportedVersion(version));
+    }
+    let params = read_params(&mut r).map_err(short)?;
+    let world = read_world(&mut r).map_err(short)?;
+    let state = read_state(&mut r).map_err(short)?;
+    if !r.is_empty() {
+        return Err(SimError::TrailingBytes);
+    }
+    Ok((params, world, state))
+}
+
+/// FNV-1a 64 over everything written to it.
+struct Fnv64(u64);
+
+impl Write for Fnv64 {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        for &b in buf {
+            self.0 ^= b as u64;
+            self.0 = self.0.wrapping_mul(0x100000001b3);
+        }
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+/// Checksum of params, world geometry (in order) and state, using the same
+/// byte encoding as the binary replay. Floats are hashed by bit pattern, so
+/// `0.0` and `-0.0` differ. Compare against a stored value before replaying to
+/// catch an edited level or param set up front.
+pub fn sim_hash(params: &Params, world: &[Rect], state: &State) -> u64 {
+    let mut h = Fnv64(0xcbf29ce484222325);
+    // Writing to Fnv64 cannot fail.
+    let _ = write_params(&mut h, params)
+        .and_then(|_| write_world(&mut h, world))
+        .and_then(|_| write_state(&mut h, state));
+    h.0
+}
+
+/// A [`Replay`] opened for scrubbing: [`seek`](Self::seek) to any frame and
+/// fork the inputs from a mid-point with [`set_input_from`](Self::set_input_from).
+///
+/// The state before every `interval`-th frame is cached as the session runs,
+/// so a seek replays at most `interval - 1` frames past the nearest snapshot
+/// (or the current position, when that is closer).
+#[derive(Clone, Debug)]
+pub struct ReplaySession {
+    replay: Replay,
+    interval: usize,
+    /// `snapshots[k]` is the state before frame `k * interval`; always contiguous.
+    snapshots: Vec<State>,
+    frame: usize,
+    state: State,
+}
+
+impl ReplaySession {
+    /// Opens `replay` at frame 0. `interval` is clamped to at least 1.
+    pub fn new(replay: Replay, interval: usize) -> Self {
+        let state = replay.initial_state;
+        Self { replay, interval: interval.max(1), snapshots: vec![state], frame: 0, state }
+    }
+
+    pub fn replay(&self) -> &Replay {
+        &self.replay
+    }
+
+    /// Frames applied so far: `state()` is the state after this many inputs.
+    pub fn frame(&self) -> usize {
+        self.frame
+    }
+
+    pub fn state(&self) -> &State {
+        &self.state
+    }
+
+    /// Moves to the state after `frame` inputs (clamped to the input count).
+    pub fn seek(&mut self, frame: usize) -> &State {
+        let target = frame.min(self.replay.inputs.len());
+        let k = (target / self.interval).min(self.snapshots.len() - 1);
+        if self.frame > target || self.frame < k * self.interval {
+            self.frame = k * self.interval;
+            self.state = self.snapshots[k];
+        }
+        while self.frame < target {
+            let buttons = Buttons::from_bits_truncate(self.replay.inputs[self.frame]);
+            step(&self.replay.params, self.replay.world.as_slice(), &mut self.state, buttons);
+            self.frame += 1;
+            if self.frame.is_multiple_of(self.interval) && self.frame / self.interval == self.snapshots.len() {
+                self.snapshots.push(self.state);
+            }
+        }
+        &self.state
+    }
+
+    /// Replaces every input from `frame` on with `inputs` (a fork), dropping
+    /// the snapshots past `frame`. A session positioned past `frame` moves
+    /// back to it; earlier positions are unaffected.
+    pub fn set_input_from(&mut self, frame: usize, inputs: &[u8]) {
+        let frame = frame.min(self.replay.inputs.len());
+        self.replay.inputs.truncate(frame);
+        self.replay.inputs.extend_from_slice(inputs);
+        self.snapshots.truncate(frame / self.interval + 1);
+        if self.frame > frame {
+            self.seek(frame);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{
+        deserialize_sim, read_replay_bin, serialize_sim, sim_hash, write_replay_bin, Replay, ReplaySession,
+        SimError, REPLAY_VERSION,
+    };
+    use crate::{step, Buttons, Params, Rect, State};
+
+    fn sample() -> Replay {
+        Replay {
+            params: Params {
+                jump_velocity: 610.0,
+                world_wrap_mode: 2.0,
+                ..Params::default()
+            },
+            world: vec![
+                Rect { x: 0.0, y: 480.0, w: 960.0, h: 60.0 },
+                Rect { x: 120.0, y: 300.0, w: 240.0, h: 18.0 },
+            ],
+            initial_state: State {
+                x: 80.0,
+                y: 436.0,
+                vx: -3.5,
+                w: 28.0,
+                h: 44.0,
+                grounded: 1,
+                coyote: 0.05,
+                jump_was_down: 1,
+                snap_suppress: 2,
+                ..State::default()
+            },
+            inputs: (0..1000u32)
+                .map(|f| if f % 7 == 0 { Buttons::JUMP.bits() } else { Buttons::RIGHT.bits() })
+                .collect(),
+        }
+    }
+
+    #[test]
+    fn binary_replay_round_trips() {
+        let replay = sample();
+        let mut bytes = Vec::new();
+        write_replay_bin(&mut bytes, &replay).unwrap();
+
+        let back = read_replay_bin(bytes.as_slice()).unwrap();
+        assert_eq!(back.params.as_slice(), replay.params.as_slice());
+        assert_eq!(back.world.len(), 2);
+        assert_eq!(back.world[1].w, 240.0);
+        let (a, b) = (back.initial_state, replay.initial_state);
+        assert_eq!((a.x, a.y, a.vx, a.vy, a.w, a.h), (b.x, b.y, b.vx, b.vy, b.w, b.h));
+        assert_eq!((a.grounded, a.jump_was_down, a.snap_suppress), (1, 1, 2));
+        assert_eq!((a.coyote, a.jump_buffer), (b.coyote, b.jump_buffer));
+        assert_eq!(back.inputs, replay.inputs);
+    }
+
+    #[test]
+    fn binary_replay_rejects_other_versions() {
+        let mut bytes = Vec::new();
+        write_replay_bin(&mut bytes, &sample()).unwrap();
+        // Older files too: v12 and earlier have the same param count in a different order.
+        for version in [REPLAY_VERSION + 1, REPLAY_VERSION - 1] {
+            bytes[4..6].copy_from_slice(&version.to_le_bytes());
+            let err = read_replay_bin(bytes.as_slice()).unwrap_err();
+            assert_eq!(err.kind(), std::io::ErrorKind::InvalidData);
+        }
+    }
+
+    #[test]
+    fn sim_hash_covers_world_params_and_state() {
+        let r = sample();
+        let base = sim_hash(&r.params, &r.world, &r.initial_state);
+        assert_eq!(base, sim_hash(&r.params, &r.world.clone(), &r.initial_state));
+
+        let mut moved = r.world.clone();
+        moved[1].x += 1.0;
+        assert_ne!(base, sim_hash(&r.params, &moved, &r.initial_state));
+
+        let swapped: Vec<Rect> = r.world.iter().rev().copied().collect();
+        assert_ne!(base, sim_hash(&r.params, &swapped, &r.initial_state));
+
+        let tuned = Params { jump_velocity: 611.0, ..r.params };
+        assert_ne!(base, sim_hash(&tuned, &r.world, &r.initial_state));
+
+        let nudged = State { y: 437.0, ..r.initial_state };
+        assert_ne!(base, sim_hash(&r.params, &r.world, &nudged));
+    }
+
+    #[test]
+    fn sim_blob_round_trips() {
+        let r = sample();
+        let state = State { airtime_frames: 12, pushing: -1, fall_distance: 33.5, ..r.initial_state };
+        let bytes = serialize_sim(&r.params, &r.world, &state);
+
+        let (params, world, back) = deserialize_sim(&bytes).unwrap();
+        assert_eq!(params.as_slice(), r.params.as_slice());
+        assert_eq!(format!("{world:?}"), format!("{:?}", r.world));
+        assert_eq!(format!("{back:?}"), format!("{state:?}"));
+        assert_eq!(sim_hash(&params, &world, &back), sim_hash(&r.params, &r.world, &state));
+    }
+
+    #[test]
+    fn binary_replay_rejects_truncated_inputs() {
+        let mut bytes = Vec::new();
+        write_replay_bin(&mut bytes, &sample()).unwrap();
+        let count_at = bytes.len() - sample().inputs.len() - 4;
+
+        bytes.pop();
+        let err = read_replay_bin(bytes.as_slice()).unwrap_err();
+        assert_eq!(err.kind(), std::io::ErrorKind::UnexpectedEof);
+
+        // A count near u32::MAX with no data behind it fails on the short read.
+        bytes[count_at..count_at + 4].copy_from_slice(&u32::MAX.to_le_bytes());
+        let err = read_replay_bin(bytes.as_slice()).unwrap_err();
+        assert_eq!(err.kind(), std::io::ErrorKind::UnexpectedEof);
+    }
+
+    #[test]
+    fn sim_blob_rejects_bad_input() {
+        let r = sample();
+        let bytes = serialize_sim(&r.params, &r.world, &r.initial_state);
+
+        let mut other = bytes.clone();
+        other[4..6].copy_from_slice(&(REPLAY_VERSION + 1).to_le_bytes());
+        assert_eq!(deserialize_sim(&other).unwrap_err(), SimError::UnsupportedVersion(REPLAY_VERSION + 1));
+        other[4..6].copy_from_slice(&(REPLAY_VERSION - 1).to_le_bytes());
+        assert_eq!(deserialize_sim(&other).unwrap_err(), SimError::UnsupportedVersion(REPLAY_VERSION - 1));
+
+        let mut replay = Vec::new();
+        write_replay_bin(&mut replay, &r).unwrap();
+        assert_eq!(deserialize_sim(&replay).unwrap_err(), SimError::BadMagic);
+
+        let mut fewer_params = bytes.clone();
+        fewer_params[6] -= 1;
+        assert_eq!(deserialize_sim(&fewer_params).unwrap_err(), SimError::ParamsMismatch);
+
+        assert_eq!(deserialize_sim(&bytes[..bytes.len() - 1]).unwrap_err(), SimError::Truncated);
+        let mut longer = bytes.clone();
+        longer.push(0);
+        assert_eq!(deserialize_sim(&longer).unwrap_err(), SimError::TrailingBytes);
+    }
+
+    #[test]
+    fn replay_session_seeks_and_forks() {
+        let run = |replay: &Replay, frames: usize| {
+            let mut s = replay.initial_state;
+            for &bits in &replay.inputs[..frames] {
+                step(&replay.params, replay.world.as_slice(), &mut s, Buttons::from_bits_truncate(bits));
+            }
+            format!("{s:?}")
+        };
+        let original = sample();
+        let mut session = ReplaySession::new(original.clone(), 60);
+
+        assert_eq!(format!("{:?}", session.seek(900)), run(&original, 900));
+        assert_eq!(session.snapshots.len(), 16);
+        // Backward, then forward past the end (clamped).
+        assert_eq!(format!("{:?}", session.seek(130)), run(&original, 130));
+        assert_eq!(session.frame(), 130);
+        assert_eq!(format!("{:?}", session.seek(5000)), run(&original, 1000));
+
+        // Fork at frame 100: hold LEFT from there on.
+        let left = vec![Buttons::LEFT.bits(); 400];
+        session.set_input_from(100, &left);
+        assert_eq!(session.frame(), 100);
+        assert_eq!(session.snapshots.len(), 2);
+        assert_eq!(format!("{:?}", session.state()), run(&original, 100));
+
+        let mut forked = original.clone();
+        forked.inputs.truncate(100);
+        forked.inputs.extend_from_slice(&left);
+        let fork_end = format!("{:?}", session.seek(500));
+        assert_eq!(fork_end, run(&forked, 500));
+        assert_ne!(fork_end, run(&original, 500));
+        assert_eq!(session.replay().inputs.len(), 500);
+    }
+}
@@ -0,0 +1,116 @@
+//! Deterministic helpers for fuzzing, property tests and benchmarks.
+//!
+//! Enabled with the `test-support` feature (always built for this crate's own
+//! unit tests). Everything here is seeded and platform-independent.
+
+use crate::{rects_intersect, Rect};
+
+/// Platform width range (px, inclusive) used by [`random_world`].
+pub const PLATFORM_W: (u32, u32) = (32, 160);
+/// Platform height range (px, inclusive) used by [`random_world`].
+pub const PLATFORM_H: (u32, u32) = (8, 32);
+
+/// Placement attempts per requested platform before [`random_world`] gives up.
+const ATTEMPTS_PER_RECT: usize = 32;
+
+/// SplitMix64: tiny, explicit, and identical on every platform.
+#[derive(Copy, Clone, Debug)]
+pub struct Rng(u64);
+
+impl Rng {
+    pub fn new(seed: u64) -> Self {
+        Self(seed)
+    }
+
+    pub fn next_u64(&mut self) -> u64 {
+        self.0 = self.0.wrapping_add(0x9e37_79b9_7f4a_7c15);
+        let mut z = self.0;
+        z = (z ^ (z >> 30)).wrapping_mul(0xbf58_476d_1ce4_e5b9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94d0_49bb_1331_11eb);
+        z ^ (z >> 31)
+    }
+
+    /// Uniform integer in `lo..=hi`.
+    pub fn range_u32(&mut self, lo: u32, hi: u32) -> u32 {
+        let span = (hi - lo) as u64 + 1;
+        lo + (self.next_u64() % span) as u32
+    }
+
+    /// Uniform float in `[0, 1)` built from the top 24 bits.
+    pub fn next_f32(&mut self) -> f32 {
+        (self.next_u64() >> 40) as f32 / (1u64 << 24) as f32
+    }
+}
+
+/// Generates up to `count` non-overlapping, pixel-aligned platforms inside
+/// `bounds`, reproducibly for a given `seed`.
+///
+/// Sizes are drawn from [`PLATFORM_W`] x [`PLATFORM_H`] (clamped to `bounds`).
+/// Placement is rejection-sampled; if `bounds` is too crowded the result holds
+/// fewer than `count` rects rather than looping forever.
+pub fn random_world(seed: u64, count: usize, bounds: Rect) -> Vec<Rect> {
+    let mut rng = Rng::new(seed);
+    let mut world: Vec<Rect> = Vec::with_capacity(count);
+
+    let bw = bounds.w.max(0.0) as u32;
+    let bh = bounds.h.max(0.0) as u32;
+    if bw == 0 || bh == 0 {
+        return world;
+    }
+
+    for _ in 0..count * ATTEMPTS_PER_RECT {
+        if world.len() == count {
+            break;
+        }
+        let w = rng.range_u32(PLATFORM_W.0.min(bw), PLATFORM_W.1.min(bw));
+        let h = rng.range_u32(PLATFORM_H.0.min(bh), PLATFORM_H.1.min(bh));
+        let r = Rect {
+            x: bounds.x.round() + rng.range_u32(0, bw - w) as f32,
+            y: bounds.y.round() + rng.range_u32(0, bh - h) as f32,
+            w: w as f32,
+            h: h as f32,
+        };
+        if !world.iter().any(|p| rects_intersect(&r, p)) {
+            world.push(r);
+        }
+    }
+
+    world
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{random_world, Rng};
+    use crate::{rects_intersect, Rect};
+
+    const BOUNDS: Rect = Rect { x: 0.0, y: 0.0, w: 960.0, h: 540.0 };
+
+    #[test]
+    fn same_seed_same_world() {
+        let a = random_world(42, 40, BOUNDS);
+        let b = random_world(42, 40, BOUNDS);
+        assert_eq!(format!("{a:?}"), format!("{b:?}"));
+
+        let c = random_world(43, 40, BOUNDS);
+        assert_ne!(format!("{a:?}"), format!("{c:?}"));
+    }
+
+    #[test]
+    fn platforms_fit_bounds_and_do_not_overlap() {
+        let world = random_world(7, 60, BOUNDS);
+        assert_eq!(world.len(), 60);
+        for (i, a) in world.iter().enumerate() {
+            assert!(a.x >= 0.0 && a.y >= 0.0 && a.x + a.w <= 960.0 && a.y + a.h <= 540.0);
+            for b in &world[i + 1..] {
+                assert!(!rects_intersect(a, b));
+            }
+        }
+    }
+
+    #[test]
+    fn rng_sequence_is_pinned() {
+        // Guards cross-platform reproducibility of saved fuzz seeds.
+        let mut rng = Rng::new(0);
+        assert_eq!(rng.next_u64(), 0xe220_a839_7b1d_cdaf);
+    }
+}
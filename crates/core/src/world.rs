@@ -0,0 +1,296 @@
+//! Collision world abstraction used by `step`.
+//!
+//! `step` only ever asks the world "which rects might touch this area?", so
+//! anything that can answer that (a plain slice, a tile grid, a broadphase)
+//! can drive the same collision code without allocating.
+
+use crate::{rects_intersect, Rect};
+
+pub trait CollisionWorld {
+    /// Calls `f(index, rect)` for every rect that may intersect `area`.
+    ///
+    /// Must include every rect that does intersect `area`; extra candidates
+    /// are fine (they are re-tested). Visit in ascending `index` order so
+    /// tie-breaks between equally close contacts stay deterministic.
+    fn for_each_near(&self, area: Rect, f: impl FnMut(usize, Rect));
+
+    /// Upper bound on the number of rects (and on `index + 1`). Bounds the
+    /// resolver's overlap passes.
+    fn rect_count(&self) -> usize;
+}
+
+/// Brute force: every rect is a candidate for every query.
+impl CollisionWorld for [Rect] {
+    fn for_each_near(&self, _area: Rect, mut f: impl FnMut(usize, Rect)) {
+        for (i, p) in self.iter().enumerate() {
+            f(i, *p);
+        }
+    }
+
+    fn rect_count(&self) -> usize {
+        self.len()
+    }
+}
+
+impl<const N: usize> CollisionWorld for [Rect; N] {
+    fn for_each_near(&self, area: Rect, f: impl FnMut(usize, Rect)) {
+        self.as_slice().for_each_near(area, f)
+    }
+
+    fn rect_count(&self) -> usize {
+        N
+    }
+}
+
+impl CollisionWorld for Vec<Rect> {
+    fn for_each_near(&self, area: Rect, f: impl FnMut(usize, Rect)) {
+        self.as_slice().for_each_near(area, f)
+    }
+
+    fn rect_count(&self) -> usize {
+        self.len()
+    }
+}
+
+/// True if any rect in `world` intersects `area`.
+pub(crate) fn any_intersect<W: CollisionWorld + ?Sized>(world: &W, area: Rect) -> bool {
+    let mut hit = false;
+    world.for_each_near(area, |_, p| hit |= rects_intersect(&area, &p));
+    hit
+}
+
+/// Load-time preprocessing that merges solid tiles and rects into as few
+/// rects as possible.
+///
+/// Rects that share a full edge (same row band touching in x, or same column
+/// band touching in y) are merged, and rects inside another are dropped, until
+/// nothing changes. The result covers exactly the same area as the input, so
+/// it collides identically; edges are only exact for coordinates that are
+/// representable sums, e.g. pixel-aligned tiles.
+#[derive(Clone, Debug, Default)]
+pub struct WorldBuilder {
+    rects: Vec<Rect>,
+}
+
+impl WorldBuilder {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn rect(mut self, r: Rect) -> Self {
+        self.rects.push(r);
+        self
+    }
+
+    pub fn rects(mut self, rects: &[Rect]) -> Self {
+        self.rects.extend_from_slice(rects);
+        self
+    }
+
+    /// Adds a row-major tile grid `cols` wide whose top-left tile sits at
+    /// `(x, y)`. Each row's solid runs are added as one rect.
+    pub fn tiles(mut self, x: f32, y: f32, tile_w: f32, tile_h: f32, cols: usize, solid: &[bool]) -> Self {
+        if cols == 0 {
+            return self;
+        }
+        for (row, cells) in solid.chunks(cols).enumerate() {
+            let top = y + row as f32 * tile_h;
+            let mut col = 0;
+            while col < cells.len() {
+                if !cells[col] {
+                    col += 1;
+                    continue;
+                }
+                let start = col;
+                while col < cells.len() && cells[col] {
+                    col += 1;
+                }
+                let left = x + start as f32 * tile_w;
+                let right = x + col as f32 * tile_w;
+                self.rects.push(Rect { x: left, y: top, w: right - left, h: tile_h });
+            }
+        }
+        self
+    }
+
+    /// Merges to a fixpoint. Degenerate rects (which never collide) are
+    /// dropped; surviving rects keep their first-seen order.
+    pub fn build(self) -> Vec<Rect> {
+        let mut rects: Vec<Rect> = self.rects.into_iter().filter(|r| r.w > 0.0 && r.h > 0.0).collect();
+        loop {
+            let mut merged = false;
+            let mut i = 0;
+            while i < rects.len() {
+                let mut j = i + 1;
+                while j < rects.len() {
+                    if let Some(m) = merge_pair(rects[i], rects[j]) {
+                        rects[i] = m;
+                        rects.remove(j);
+                        merged = true;
+                        j = i + 1;
+                    } else {
+                        j += 1;
+                    }
+                }
+                i += 1;
+            }
+            if !merged {
+                return rects;
+            }
+        }
+    }
+}
+
+/// The single rect covering exactly `a ∪ b`, if there is one.
+fn merge_pair(a: Rect, b: Rect) -> Option<Rect> {
+    if contains(&a, &b) {
+        return Some(a);
+    }
+    if contains(&b, &a) {
+        return Some(b);
+    }
+    if a.y == b.y && a.h == b.h && a.x <= b.x + b.w && b.x <= a.x + a.w {
+        let x = a.x.min(b.x);
+        return Some(Rect { x, y: a.y, w: (a.x + a.w).max(b.x + b.w) - x, h: a.h });
+    }
+    if a.x == b.x && a.w == b.w && a.y <= b.y + b.h && b.y <= a.y + a.h {
+        let y = a.y.min(b.y);
+        return Some(Rect { x: a.x, y, w: a.w, h: (a.y + a.h).max(b.y + b.h) - y });
+    }
+    None
+}
+
+fn contains(outer: &Rect, inner: &Rect) -> bool {
+    inner.x >= outer.x
+        && inner.y >= outer.y
+        && inner.x + inner.w <= outer.x + outer.w
+        && inner.y + inner.h <= outer.y + outer.h
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{any_intersect, CollisionWorld, WorldBuilder};
+    use crate::testing::Rng;
+    use crate::{rects_intersect, step, Buttons, Params, Rect, State};
+
+    /// Test-only broadphase: rects bucketed into 64px columns.
+    struct Columns {
+        rects: Vec<Rect>,
+        columns: Vec<Vec<usize>>,
+    }
+
+    const COL_W: f32 = 64.0;
+
+    impl Columns {
+        fn new(rects: &[Rect]) -> Self {
+            let mut columns = vec![Vec::new(); 32];
+            for (i, r) in rects.iter().enumerate() {
+                let lo = (r.x / COL_W).floor().max(0.0) as usize;
+                let hi = ((r.x + r.w) / COL_W).floor().max(0.0) as usize;
+                for col in columns.iter_mut().take(hi + 1).skip(lo) {
+                    col.push(i);
+                }
+            }
+            Self { rects: rects.to_vec(), columns }
+        }
+    }
+
+    impl CollisionWorld for Columns {
+        fn for_each_near(&self, area: Rect, mut f: impl FnMut(usize, Rect)) {
+            let lo = (area.x / COL_W).floor().max(0.0) as usize;
+            let hi = ((area.x + area.w) / COL_W).floor().max(0.0) as usize;
+            let mut near: Vec<usize> = self.columns.iter().take(hi + 1).skip(lo).flatten().copied().collect();
+            near.sort_unstable();
+            near.dedup();
+            for i in near {
+                if rects_intersect(&area, &self.rects[i]) {
+                    f(i, self.rects[i]);
+                }
+            }
+        }
+
+        fn rect_count(&self) -> usize {
+            self.rects.len()
+        }
+    }
+
+    const WORLD: [Rect; 4] = [
+        Rect { x: 0.0, y: 480.0, w: 960.0, h: 60.0 },
+        Rect { x: 300.0, y: 380.0, w: 120.0, h: 20.0 },
+        Rect { x: 520.0, y: 300.0, w: 40.0, h: 180.0 },
+        Rect { x: 180.0, y: 330.0, w: 60.0, h: 16.0 },
+    ];
+
+    fn trace<W: CollisionWorld + ?Sized>(world: &W) -> Vec<String> {
+        let params = Params { world_wrap_mode: 0.0, ..Params::default() };
+        let mut state = State { x: 40.0, y: 480.0 - 44.0, w: 28.0, h: 44.0, grounded: 1, ..State::default() };
+        (0..240)
+            .map(|frame| {
+                let mut buttons = if frame < 160 { Buttons::RIGHT } else { Buttons::LEFT };
+                if frame % 45 == 5 {
+                    buttons |= Buttons::JUMP;
+                }
+                let ev = step(&params, world, &mut state, buttons);
+                format!("{state:?} {ev:?}")
+            })
+            .collect()
+    }
+
+    #[test]
+    fn every_world_type_drives_the_same_step() {
+        let slice: &[Rect] = &WORLD;
+        let expected = trace(slice);
+
+        assert_eq!(trace(&WORLD), expected);
+        assert_eq!(trace(&WORLD.to_vec()), expected);
+        assert_eq!(trace(&Columns::new(&WORLD)), expected);
+    }
+
+    #[test]
+    fn builder_merges_tile_row_into_one_rect() {
+        let merged = WorldBuilder::new().tiles(32.0, 480.0, 16.0, 16.0, 10, &[true; 10]).build();
+        assert_eq!(merged.len(), 1);
+        let r = merged[0];
+        assert_eq!((r.x, r.y, r.w, r.h), (32.0, 480.0, 160.0, 16.0));
+
+        // A 2x2 block collapses too; rects that only share a corner don't.
+        let block = WorldBuilder::new()
+            .tiles(0.0, 0.0, 8.0, 8.0, 3, &[true, true, false, true, true, false, false, false, true])
+            .build();
+        assert_eq!(block.len(), 2);
+        assert_eq!((block[0].w, block[0].h), (16.0, 16.0));
+    }
+
+    #[test]
+    fn builder_output_is_collision_equivalent() {
+        const COLS: usize = 24;
+        const ROWS: usize = 16;
+        const TILE: f32 = 16.0;
+
+        let mut rng = Rng::new(0x5eed);
+        for _ in 0..50 {
+            let solid: Vec<bool> = (0..COLS * ROWS).map(|_| rng.next_f32() < 0.45).collect();
+            let tiles = WorldBuilder::new().tiles(0.0, 0.0, TILE, TILE, COLS, &solid).rects;
+            let single: Vec<Rect> = solid
+                .iter()
+                .enumerate()
+                .filter(|(_, &on)| on)
+                .map(|(i, _)| Rect { x: (i % COLS) as f32 * TILE, y: (i / COLS) as f32 * TILE, w: TILE, h: TILE })
+                .collect();
+            let merged = WorldBuilder::new().rects(&single).build();
+            assert!(merged.len() <= single.len());
+
+            for _ in 0..400 {
+                let area = Rect {
+                    x: rng.range_u32(0, COLS as u32 * 16) as f32 - 4.0,
+                    y: rng.range_u32(0, ROWS as u32 * 16) as f32 - 4.0,
+                    w: rng.range_u32(1, 40) as f32,
+                    h: rng.range_u32(1, 40) as f32,
+                };
+                let expected = any_intersect(single.as_slice(), area);
+                assert_eq!(any_intersect(merged.as_slice(), area), expected, "{area:?}");
+                assert_eq!(any_intersect(tiles.as_slice(), area), expected, "{area:?}");
+            }
+        }
+    }
+}
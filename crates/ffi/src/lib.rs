@@ -1,4 +1,13 @@
-use platlab_core::{Buttons, Events, Params, Rect, State};
+#![allow(clippy::not_unsafe_ptr_arg_deref)]
+
+use platlab_core::{Buttons, Events, Input, Params, Rect, State};
+
+/// `platlab_core::ABI_VERSION`. Hosts should check it before passing any struct
+/// across, since a mismatched `Params`/`State` mirror is written past its end.
+#[no_mangle]
+pub extern "C" fn core_abi_version() -> u32 {
+    platlab_core::ABI_VERSION
+}
 
 #[no_mangle]
 pub extern "C" fn core_default_params(out: *mut Params) {
@@ -7,16 +16,28 @@ pub extern "C" fn core_default_params(out: *mut Params) {
 
 #[no_mangle]
 pub extern "C" fn core_init_state(out: *mut State, x: f32, y: f32, w: f32, h: f32) {
-    let mut s = State::default();
-    s.x = x;
-    s.y = y;
-    s.w = w;
-    s.h = h;
-    s.grounded = 0;
-    s.jump_was_down = 0;
+    let s = State {
+        x,
+        y,
+        w,
+        h,
+        grounded: 0,
+        jump_was_down: 0,
+        ..State::default()
+    };
     unsafe { *out = s; }
 }
 
+/// Like `core_init_state`, with the player already moving at `(vx, vy)` px/s.
+#[no_mangle]
+pub extern "C" fn core_init_state_v(out: *mut State, x: f32, y: f32, w: f32, h: f32, vx: f32, vy: f32) {
+    core_init_state(out, x, y, w, h);
+    unsafe {
+        (*out).vx = vx;
+        (*out).vy = vy;
+    }
+}
+
 #[no_mangle]
 pub extern "C" fn core_step(
     params: *const Params,
@@ -32,3 +53,67 @@ pub extern "C" fn core_step(
 
     platlab_core::step(p, world, s, buttons)
 }
+
+/// Like `core_step`, taking an `Input` by pointer so fields can be added
+/// without changing the signature.
+#[no_mangle]
+pub extern "C" fn core_step_input(
+    params: *const Params,
+    world_rects: *const Rect,
+    world_len: usize,
+    state: *mut State,
+    input: *const Input,
+) -> Events {
+    let p = unsafe { &*params };
+    let s = unsafe { &mut *state };
+    let world = unsafe { std::slice::from_raw_parts(world_rects, world_len) };
+    let input = unsafe { &*input };
+
+    platlab_core::step_input(p, world, s, input)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{core_default_params, core_init_state, core_init_state_v, core_step, core_step_input};
+    use platlab_core::{Buttons, Input, Params, Rect, State};
+
+    #[test]
+    fn step_input_analog_axis_scales_speed() {
+        let mut params = Params::default();
+        core_default_params(&mut params);
+        let world = [Rect { x: 0.0, y: 480.0, w: 2000.0, h: 60.0 }];
+
+        let mut full = State::default();
+        let mut half = State::default();
+        core_init_state(&mut full, 100.0, 436.0, 28.0, 44.0);
+        core_init_state(&mut half, 100.0, 436.0, 28.0, 44.0);
+
+        let half_right = Input { bits: 0, move_x: 0.5, jump_subframe: 0.0 };
+        for _ in 0..60 {
+            core_step(&params, world.as_ptr(), world.len(), &mut full, Buttons::RIGHT.bits());
+            core_step_input(&params, world.as_ptr(), world.len(), &mut half, &half_right);
+        }
+        assert!((full.vx - params.ground_max_speed).abs() < 1e-3);
+        assert!((half.vx - 0.5 * params.ground_max_speed).abs() < 1e-3);
+
+        // move_x = 0 falls back to the button bits.
+        let left = Input { bits: Buttons::LEFT.bits(), ..Input::default() };
+        let (mut a, mut b) = (full, full);
+        core_step(&params, world.as_ptr(), world.len(), &mut a, Buttons::LEFT.bits());
+        core_step_input(&params, world.as_ptr(), world.len(), &mut b, &left);
+        assert_eq!((a.x, a.vx), (b.x, b.vx));
+    }
+
+    #[test]
+    fn init_state_v_moves_on_first_frame() {
+        let params = Params::default();
+        let world: [Rect; 0] = [];
+        let mut s = State::default();
+        core_init_state_v(&mut s, 100.0, 100.0, 28.0, 44.0, 300.0, -400.0);
+        assert_eq!((s.vx, s.vy, s.w, s.h), (300.0, -400.0, 28.0, 44.0));
+
+        core_step(&params, world.as_ptr(), world.len(), &mut s, 0);
+        assert!(s.x > 100.0);
+        assert!(s.y < 100.0);
+    }
+}
@@ -1,30 +1,55 @@
 use wasm_bindgen::prelude::*;
-use platlab_core::{Buttons, Params, Rect, State};
+use platlab_core::{Buttons, Events, Params, Rect, State};
 
 #[wasm_bindgen]
 pub struct Core {
     params: Params,
     state: State,
     world: Vec<Rect>,
+    events: Events,
+}
+
+impl Default for Core {
+    fn default() -> Self {
+        Self::new()
+    }
 }
 
 #[wasm_bindgen]
 impl Core {
     #[wasm_bindgen(constructor)]
     pub fn new() -> Core {
-        let mut params = Params::default();
-        params.world_w = 960.0;
-        params.world_wrap_mode = 2.0;
+        let params = Params {
+            world_w: 960.0,
+            world_wrap_mode: 2.0,
+            ..Params::default()
+        };
 
-        let mut state = State::default();
-        state.x = 80.0;
-        state.y = 480.0 - 44.0;
-        state.w = 28.0;
-        state.h = 44.0;
+        let state = State {
+            x: 80.0,
+            y: 480.0 - 44.0,
+            w: 28.0,
+            h: 44.0,
+            ..State::default()
+        };
 
         let world = vec![Rect { x: 0.0, y: 480.0, w: 960.0, h: 60.0 }];
 
-        Core { params, state, world }
+        Core { params, state, world, events: Events::default() }
+    }
+
+    /// One-call setup: params JSON (as for `set_params_json`), packed world
+    /// rects (as for `set_world`), and the player's start rect. Unparseable
+    /// params keep the defaults from `new`; a non-finite start position or
+    /// non-positive size falls back to the `new` player. Every packed rect keeps
+    /// its index, so `update_rect`/`remove_rect` and `bonk_index`/`wall_index`
+    /// line up with the caller's array: zero-size rects are kept (they never
+    /// collide) and a rect with a non-finite value is stored as an empty one.
+    pub fn with_config(params_json: &str, world: Box<[f32]>, start_x: f32, start_y: f32, w: f32, h: f32) -> Core {
+        let mut core = Core::new();
+        core.set_params_json(params_json);
+        core.configure(&world, start_x, start_y, w, h);
+        core
     }
 
     pub fn reset(&mut self, x: f32, y: f32, w: f32, h: f32) {
@@ -35,6 +60,19 @@ impl Core {
         self.state.h = h;
     }
 
+    /// `reset`, with the player already moving at `(vx, vy)` px/s (scripted
+    /// spawns such as conveyor drops or launched entries).
+    pub fn reset_with_velocity(&mut self, x: f32, y: f32, w: f32, h: f32, vx: f32, vy: f32) {
+        self.reset(x, y, w, h);
+        self.state.vx = vx;
+        self.state.vy = vy;
+    }
+
+    /// Move to `(x, y)` keeping size; clears velocity, timers and grounding.
+    pub fn respawn(&mut self, x: f32, y: f32) {
+        platlab_core::respawn(&mut self.state, x, y);
+    }
+
     /// Packed rects: [x,y,w,h, x,y,w,h, ...]
     pub fn set_world(&mut self, rects: Box<[f32]>) {
         let a = rects.into_vec();
@@ -44,6 +82,43 @@ impl Core {
         }
     }
 
+    /// Appends one rect and returns its index.
+    pub fn add_rect(&mut self, x: f32, y: f32, w: f32, h: f32) -> usize {
+        self.world.push(Rect { x, y, w, h });
+        self.world.len() - 1
+    }
+
+    /// Replaces the rect at `index`; out-of-range indices are ignored.
+    pub fn update_rect(&mut self, index: usize, x: f32, y: f32, w: f32, h: f32) {
+        if let Some(r) = self.world.get_mut(index) {
+            *r = Rect { x, y, w, h };
+        }
+    }
+
+    /// Removes the rect at `index`, shifting later rects down by one.
+    /// Out-of-range indices are ignored.
+    pub fn remove_rect(&mut self, index: usize) {
+        if index < self.world.len() {
+            self.world.remove(index);
+        }
+    }
+
+    pub fn clear_world(&mut self) {
+        self.world.clear();
+    }
+
+    pub fn world_len(&self) -> usize {
+        self.world.len()
+    }
+
+    /// Indices of world rects overlapping the view rect, including rects
+    /// across the seam when the world wraps.
+    pub fn rects_in_view(&self, x: f32, y: f32, w: f32, h: f32) -> Box<[u32]> {
+        let mut out = Vec::new();
+        platlab_core::rects_in_view_wrapped(&self.params, &Rect { x, y, w, h }, &self.world, &mut out);
+        out.into_iter().map(|i| i as u32).collect()
+    }
+
     /// Minimal params update: expects JSON with matching field names.
     /// (You’ll likely replace this with serde_json later.)
     pub fn set_params_json(&mut self, json: &str) {
@@ -63,29 +138,77 @@ impl Core {
             setf!("ground_decel", ground_decel);
             setf!("ground_friction", ground_friction);
             setf!("run_multiplier", run_multiplier);
+            setf!("run_ramp_time", run_ramp_time);
             setf!("air_max_speed", air_max_speed);
             setf!("air_accel", air_accel);
             setf!("air_decel", air_decel);
             setf!("air_drag", air_drag);
+            setf!("air_max_speed_falling", air_max_speed_falling);
+            setf!("air_accel_falling", air_accel_falling);
             setf!("gravity_up", gravity_up);
             setf!("gravity_down", gravity_down);
             setf!("terminal_velocity", terminal_velocity);
+            setf!("max_rise_speed", max_rise_speed);
             setf!("fast_fall_multiplier", fast_fall_multiplier);
+            setf!("wall_proximity_gravity_scale", wall_proximity_gravity_scale);
+            setf!("auto_fast_fall_after", auto_fast_fall_after);
+            setf!("gravity_blend", gravity_blend);
+            setf!("integration_mode", integration_mode);
             setf!("jump_velocity", jump_velocity);
             setf!("jump_cut_multiplier", jump_cut_multiplier);
+            setf!("min_jump_velocity", min_jump_velocity);
+            setf!("jump_cut_gravity_boost", jump_cut_gravity_boost);
+            setf!("jump_cut_boost_frames", jump_cut_boost_frames);
             setf!("coyote_time", coyote_time);
             setf!("jump_buffer", jump_buffer);
+            setf!("coyote_frames", coyote_frames);
+            setf!("jump_buffer_frames", jump_buffer_frames);
+            setf!("grounded_grace_frames", grounded_grace_frames);
+            setf!("auto_bounce", auto_bounce);
             setf!("snap_to_ground", snap_to_ground);
+            setf!("foot_width_fraction", foot_width_fraction);
             setf!("max_step_px", max_step_px);
+            setf!("max_step_px_x", max_step_px_x);
+            setf!("max_step_px_y", max_step_px_y);
+            setf!("max_substeps", max_substeps);
+            setf!("min_substeps", min_substeps);
+            setf!("min_max_step", min_max_step);
+            setf!("jump_snap_suppress_frames", jump_snap_suppress_frames);
             setf!("world_w", world_w);
+            setf!("world_h", world_h);
             setf!("world_wrap_mode", world_wrap_mode);
+            setf!("world_bounce_restitution", world_bounce_restitution);
+            setf!("bonk_restitution", bonk_restitution);
+            setf!("max_fall_distance", max_fall_distance);
+            setf!("input_deadzone", input_deadzone);
+            setf!("input_smoothing", input_smoothing);
+            setf!("freeze_axes", freeze_axes);
+            setf!("noclip", noclip);
+            setf!("disabled_events", disabled_events);
         }
     }
 
+    /// Step once (60Hz) and return the numeric state only:
+    /// `[x, y, vx, vy, grounded, coyote, jump_buffer]`. Read `last_events` when
+    /// the frame's events are needed.
+    pub fn step_packed(&mut self, input_bits: u8) -> Box<[f32]> {
+        self.advance(input_bits);
+        let s = &self.state;
+        Box::new([s.x, s.y, s.vx, s.vy, s.grounded as f32, s.coyote, s.jump_buffer])
+    }
+
+    /// Events from the most recent step as `EventMask` bits (JUMPED = 1,
+    /// LANDED = 2, BONKED = 4, GROUND_REASON = 8 for any grounding, FAST_FALLING = 16,
+    /// LEFT_GROUND = 32, FELL_TOO_FAR = 64, JUMP_WASTED = 128, WALL = 256 for a
+    /// wall contact).
+    pub fn last_events(&self) -> u16 {
+        self.events.mask().bits()
+    }
+
     /// Step once (60Hz) and return state+events as a JS object.
     pub fn step(&mut self, input_bits: u8) -> JsValue {
-        let buttons = Buttons::from_bits_truncate(input_bits);
-        let ev = platlab_core::step(&self.params, &self.world, &mut self.state, buttons);
+        self.advance(input_bits);
+        let ev = self.events;
 
         let obj = js_sys::Object::new();
         js_sys::Reflect::set(&obj, &"x".into(), &JsValue::from_f64(self.state.x as f64)).unwrap();
@@ -98,7 +221,148 @@ impl Core {
         js_sys::Reflect::set(&obj, &"jumped".into(), &JsValue::from_bool(ev.jumped != 0)).unwrap();
         js_sys::Reflect::set(&obj, &"landed".into(), &JsValue::from_bool(ev.landed != 0)).unwrap();
         js_sys::Reflect::set(&obj, &"bonked".into(), &JsValue::from_bool(ev.bonked != 0)).unwrap();
+        js_sys::Reflect::set(&obj, &"ground_reason".into(), &JsValue::from_f64(ev.ground_reason as f64)).unwrap();
+        js_sys::Reflect::set(&obj, &"fast_falling".into(), &JsValue::from_bool(ev.fast_falling != 0)).unwrap();
+        js_sys::Reflect::set(&obj, &"numeric_fault".into(), &JsValue::from_bool(ev.numeric_fault != 0)).unwrap();
+        js_sys::Reflect::set(&obj, &"left_ground".into(), &JsValue::from_bool(ev.left_ground != 0)).unwrap();
+        js_sys::Reflect::set(&obj, &"fell_too_far".into(), &JsValue::from_bool(ev.fell_too_far != 0)).unwrap();
+        js_sys::Reflect::set(&obj, &"jump_wasted".into(), &JsValue::from_bool(ev.jump_wasted != 0)).unwrap();
+        js_sys::Reflect::set(&obj, &"land_toi".into(), &JsValue::from_f64(ev.land_toi as f64)).unwrap();
+        js_sys::Reflect::set(&obj, &"vx_pre_collision".into(), &JsValue::from_f64(ev.vx_pre_collision as f64)).unwrap();
+        js_sys::Reflect::set(&obj, &"vy_pre_collision".into(), &JsValue::from_f64(ev.vy_pre_collision as f64)).unwrap();
+        js_sys::Reflect::set(&obj, &"jump_kind".into(), &JsValue::from_f64(ev.jump_kind as f64)).unwrap();
+        js_sys::Reflect::set(&obj, &"bonk_index".into(), &JsValue::from_f64(ev.bonk_index as f64)).unwrap();
+        js_sys::Reflect::set(&obj, &"wall_index".into(), &JsValue::from_f64(ev.wall_index as f64)).unwrap();
 
         JsValue::from(obj)
     }
 }
+
+impl Core {
+    fn advance(&mut self, input_bits: u8) {
+        let buttons = Buttons::from_bits_truncate(input_bits);
+        self.events = platlab_core::step(&self.params, &self.world, &mut self.state, buttons);
+    }
+
+    /// Native half of `with_config` (no JS values involved).
+    fn configure(&mut self, world: &[f32], start_x: f32, start_y: f32, w: f32, h: f32) {
+        self.world = world
+            .chunks_exact(4)
+            .map(|c| if c.iter().all(|v| v.is_finite()) { Rect { x: c[0], y: c[1], w: c[2], h: c[3] } } else { Rect::default() })
+            .collect();
+
+        let valid = [start_x, start_y, w, h].iter().all(|v| v.is_finite()) && w > 0.0 && h > 0.0;
+        if valid {
+            self.reset(start_x, start_y, w, h);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{Buttons, Core, Rect};
+
+    #[test]
+    fn incremental_world_edits() {
+        let mut core = Core::new();
+        assert_eq!(core.world_len(), 1);
+
+        let i = core.add_rect(100.0, 300.0, 64.0, 16.0);
+        assert_eq!(i, 1);
+
+        core.update_rect(i, 120.0, 280.0, 64.0, 16.0);
+        let r = core.world[i];
+        assert_eq!((r.x, r.y, r.w, r.h), (120.0, 280.0, 64.0, 16.0));
+        let floor: Rect = core.world[0];
+        assert_eq!(floor.y, 480.0);
+
+        // Out-of-range edits are ignored.
+        core.update_rect(7, 0.0, 0.0, 1.0, 1.0);
+        core.remove_rect(7);
+        assert_eq!(core.world_len(), 2);
+
+        core.remove_rect(0);
+        assert_eq!(core.world_len(), 1);
+        assert_eq!(core.world[0].x, 120.0);
+
+        core.clear_world();
+        assert_eq!(core.world_len(), 0);
+    }
+
+    #[test]
+    fn configure_builds_custom_core() {
+        let mut core = Core::new();
+        let world = [0.0, 500.0, 640.0, 40.0, 100.0, 400.0, 80.0, 16.0, f32::NAN, 0.0, 1.0, 1.0, 9.0];
+        core.configure(&world, 32.0, 456.0, 20.0, 44.0);
+
+        assert_eq!(core.world_len(), 3);
+        assert_eq!(core.world[1].x, 100.0);
+        let r = core.world[2];
+        assert_eq!((r.w, r.h), (0.0, 0.0));
+        let s = core.state;
+        assert_eq!((s.x, s.y, s.w, s.h), (32.0, 456.0, 20.0, 44.0));
+
+        // Bad player rect keeps the previous one.
+        core.configure(&world, 0.0, 0.0, -1.0, 44.0);
+        assert_eq!(core.state.w, 20.0);
+    }
+
+    #[test]
+    fn configure_keeps_indices_past_bad_rects() {
+        let mut core = Core::new();
+        // A NaN rect and a zero-width rect before the floor the player lands on.
+        let world = [f32::NAN, 0.0, 10.0, 10.0, 50.0, 50.0, 0.0, 10.0, 0.0, 480.0, 640.0, 60.0];
+        core.configure(&world, 100.0, 400.0, 28.0, 44.0);
+        assert_eq!(core.world_len(), 3);
+        assert_eq!(core.world[2].y, 480.0);
+
+        let mut landed = false;
+        for _ in 0..60 {
+            core.advance(0);
+            landed |= core.events.landed != 0;
+        }
+        assert!(landed);
+        assert_eq!(core.state.y, 436.0);
+
+        // Index 2 is still the floor for the caller's edits.
+        core.update_rect(2, 0.0, 500.0, 640.0, 40.0);
+        assert_eq!(core.world[2].y, 500.0);
+        core.remove_rect(2);
+        assert_eq!(core.world_len(), 2);
+    }
+
+    #[test]
+    fn reset_with_velocity_moves_on_first_frame() {
+        let mut core = Core::new();
+        core.clear_world();
+        core.reset_with_velocity(100.0, 100.0, 28.0, 44.0, -300.0, 200.0);
+        assert_eq!((core.state.vx, core.state.vy), (-300.0, 200.0));
+
+        let world = core.world.clone();
+        platlab_core::step(&core.params, &world, &mut core.state, platlab_core::Buttons::empty());
+        assert!(core.state.x < 100.0);
+        assert!(core.state.y > 100.0);
+    }
+
+    #[test]
+    fn last_events_bits_match_event_fields() {
+        let mut core = Core::new();
+        let mut seen = 0u16;
+        for frame in 0..120u32 {
+            let bits = if frame % 40 == 1 { Buttons::JUMP | Buttons::RIGHT } else { Buttons::RIGHT };
+            let packed = core.step_packed(bits.bits());
+            assert_eq!(packed[0], core.state.x);
+
+            let ev = core.events;
+            let mask = core.last_events();
+            assert_eq!(mask & 1 != 0, ev.jumped != 0);
+            assert_eq!(mask & 2 != 0, ev.landed != 0);
+            assert_eq!(mask & 4 != 0, ev.bonked != 0);
+            assert_eq!(mask & 8 != 0, ev.ground_reason != 0);
+            assert_eq!(mask & 16 != 0, ev.fast_falling != 0);
+            assert_eq!(mask & 32 != 0, ev.left_ground != 0);
+            seen |= mask;
+        }
+        assert_eq!(seen & 0b10_1011, 0b10_1011, "jumped, landed, grounded and left_ground all fired");
+    }
+}